@@ -0,0 +1,79 @@
+// Copyright 2018 The Synthesizer IO Authors.
+// 
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+// 
+//     https://www.apache.org/licenses/LICENSE-2.0
+// 
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Benchmarks for scope rendering.
+
+#![feature(test)]
+
+extern crate test;
+extern crate synthesize_scope;
+
+#[cfg(test)]
+mod bench {
+    use test::Bencher;
+    use synthesize_scope::Scope;
+
+    // Draw 100 diagonal segments of the given length, either analytically or
+    // by stepping dots. Used to choose `LINE_STEP_CROSSOVER`.
+    fn lines(b: &mut Bencher, len: f32, analytic: bool) {
+        let mut scope = Scope::new(640, 480);
+        let d = len * 0.5f32.sqrt();
+        b.iter(|| {
+            for i in 0..100 {
+                let x = 100.0 + 3.3 * i as f32;
+                if analytic {
+                    scope.add_line_analytic(x, 100.0, x + d, 100.0 + d, 1.0, 0.1);
+                } else {
+                    scope.add_line_step(x, 100.0, x + d, 100.0 + d, 1.0, 0.1);
+                }
+            }
+        })
+    }
+
+    #[bench]
+    fn line_analytic_2(b: &mut Bencher) { lines(b, 2.0, true) }
+
+    #[bench]
+    fn line_step_2(b: &mut Bencher) { lines(b, 2.0, false) }
+
+    #[bench]
+    fn line_analytic_3(b: &mut Bencher) { lines(b, 3.0, true) }
+
+    #[bench]
+    fn line_step_3(b: &mut Bencher) { lines(b, 3.0, false) }
+
+    #[bench]
+    fn line_analytic_4(b: &mut Bencher) { lines(b, 4.0, true) }
+
+    #[bench]
+    fn line_step_4(b: &mut Bencher) { lines(b, 4.0, false) }
+
+    #[bench]
+    fn line_analytic_8(b: &mut Bencher) { lines(b, 8.0, true) }
+
+    #[bench]
+    fn line_step_8(b: &mut Bencher) { lines(b, 8.0, false) }
+
+    #[bench]
+    fn line_analytic_16(b: &mut Bencher) { lines(b, 16.0, true) }
+
+    #[bench]
+    fn line_step_16(b: &mut Bencher) { lines(b, 16.0, false) }
+
+    #[bench]
+    fn line_analytic_32(b: &mut Bencher) { lines(b, 32.0, true) }
+
+    #[bench]
+    fn line_step_32(b: &mut Bencher) { lines(b, 32.0, false) }
+}
@@ -16,6 +16,10 @@
 
 extern crate fearless_simd;
 
+mod state;
+
+pub use state::StateError;
+
 #[cfg(target_arch = "x86")]
 use std::arch::x86::*;
 
@@ -25,8 +29,46 @@ use std::arch::x86_64::*;
 #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 use fearless_simd::{AvxF32, SimdF32};
 
-/// The box beyond which the gaussian can be clipped, as a multiple of radius.
-const CLIP_FACTOR: f32 = 2.5;
+/// The default box beyond which the gaussian can be clipped, as a multiple
+/// of radius.
+const DEFAULT_CLIP_FACTOR: f32 = 2.5;
+
+/// The sweep rate at which auto intensity leaves the intensity unchanged.
+const REFERENCE_SWEEP: f32 = 0.002;
+
+/// The fraction of the height filled by the peaks in auto gain mode.
+const AUTO_GAIN_FILL: f32 = 0.8;
+
+/// The time constant for the auto gain peak to fall, in seconds.
+const AUTO_GAIN_RELEASE: f32 = 1.0;
+
+/// The most that auto gain boosts a quiet signal (40 dB), so that silence
+/// isn't magnified without limit.
+const AUTO_GAIN_MAX: f32 = 100.0;
+
+/// The smallest gain accepted by `set_gain`.
+const MIN_GAIN: f32 = 1e-6;
+
+/// The range of sweep rates accepted by `set_sweep`; at the maximum, each
+/// sample crosses the whole width.
+const MIN_SWEEP: f32 = 1e-9;
+const MAX_SWEEP: f32 = 1.0;
+
+/// The shortest persistence accepted by `set_persistence`, in samples.
+const MIN_TC: f32 = 1.0;
+
+/// Lines shorter than this (as a multiple of radius) are drawn as a sequence
+/// of dots, which is faster than the analytic method for short segments.
+/// The `line_*` benchmarks in `benches/bench.rs` show stepping winning up
+/// to about 4-6 radii for diagonal segments.
+pub const LINE_STEP_CROSSOVER: f32 = 6.0;
+
+// Dots up to this many pixels wide have their horizontal factors precomputed.
+const MAX_DOT_COLS: usize = 16;
+
+/// The maximum spacing of dots in `add_line_step`, as a multiple of radius.
+/// At this spacing the ripple from the individual gaussians is negligible.
+const STEP_SPACING: f32 = 1.0;
 
 pub struct Scope {
     width: usize,
@@ -36,6 +78,12 @@ pub struct Scope {
     // time constant for fade, in _samples_
     tc: f32,
 
+    sample_rate: f32,
+
+    // persistence in seconds, if it was set that way, for following changes
+    // in sample rate
+    persistence_secs: Option<f32>,
+
     // fraction of scope width per sample
     sweep: f32,
 
@@ -48,6 +96,238 @@ pub struct Scope {
     xylast: Option<(f32, f32)>,
 
     state: ScopeState,
+
+    // condition for starting a sweep; None free-runs
+    trigger: Option<Trigger>,
+
+    // samples spent waiting for the trigger, for falling back to free-running
+    trigger_wait: usize,
+
+    // sample position within the symbol period, for eye diagrams
+    eye_phase: usize,
+
+    blend_mode: BlendMode,
+
+    // scale intensity with sweep rate, for constant brightness per pixel
+    auto_intensity: bool,
+
+    // total number of samples provided to `provide_samples`
+    n_samples: u64,
+
+    // sample count at which the trigger last fired
+    last_trigger: Option<u64>,
+
+    // box beyond which the gaussian is clipped, as a multiple of radius
+    clip_factor: f32,
+
+    mirror: MirrorMode,
+
+    measurement: Option<MeasurementWindow>,
+
+    // amplitude histogram for `provide_density`, one bin per row
+    density: Vec<f32>,
+
+    // per-channel (gain, vertical offset as a fraction of height)
+    channel_scales: Vec<(f32, f32)>,
+
+    // position of the zero line above the center, as a fraction of height
+    vertical_offset: f32,
+
+    vignette: f32,
+
+    // set when a sweep finishes, cleared by `take_sweep_completed`
+    sweep_completed: bool,
+
+    envelope_fill: bool,
+
+    // column being accumulated in envelope fill mode, with its min and max y
+    envelope_column: Option<(usize, f32, f32)>,
+
+    // custom mapping from glow to brightness, replacing the default curve
+    intensity_lut: Option<Vec<f32>>,
+
+    auto_gain: bool,
+
+    // smoothed peak amplitude, for auto gain
+    peak: f32,
+
+    // gain in use for the current block, which auto gain may reduce
+    effective_gain: f32,
+
+    // channels beyond the first, which is `glow`
+    channels: Vec<ChannelGlow>,
+
+    // beam position (as a fraction of the width) for each sample of the last
+    // block given to channel 0, or None while waiting for a trigger
+    sweep_positions: Vec<Option<f32>>,
+}
+
+// The glow of a channel other than the first, and the last point of its trace.
+struct ChannelGlow {
+    glow: Vec<f32>,
+    xylast: Option<(f32, f32)>,
+}
+
+/// The colors of the channels after the first, which uses the color map.
+const CHANNEL_TINTS: [[f32; 3]; 3] = [
+    [255.0, 160.0, 32.0],
+    [255.0, 64.0, 192.0],
+    [96.0, 128.0, 255.0],
+];
+
+/// How a new contribution is combined with the existing glow.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlendMode {
+    /// Contributions are summed (the default).
+    Add,
+    /// Each pixel keeps the maximum contribution, so overlapping traces
+    /// don't over-brighten.
+    Max,
+}
+
+/// A reflection applied to the display in `as_rgba`, for symmetric
+/// visualizations.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MirrorMode {
+    /// No reflection (the default).
+    None,
+    /// The left half is reflected onto the right half.
+    Horizontal,
+    /// The top half is reflected onto the bottom half.
+    Vertical,
+    /// Both of the above, so the top left quadrant fills the display.
+    Both,
+}
+
+/// The mapping from glow to pixel color used by `as_rgba_with`.
+///
+/// Each channel is `scale * sqrt(glow + offset)`, which mimics the response
+/// of a phosphor; the offsets give the unlit phosphor its tint. The beam
+/// emits light, so it is added to the background color, and the alpha is the
+/// larger of the background alpha and the brightest channel of the beam.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ColorMap {
+    pub scale: [f32; 3],
+    pub offset: [f32; 3],
+    pub background: [u8; 4],
+}
+
+impl ColorMap {
+    /// The classic green phosphor, used by `as_rgba`.
+    pub fn green() -> ColorMap {
+        ColorMap { scale: [64.0, 255.0, 224.0], offset: [0.001, 0.05, 0.13],
+            background: [0, 0, 0, 255] }
+    }
+
+    /// An amber phosphor, as on many monochrome terminals.
+    pub fn amber() -> ColorMap {
+        ColorMap { scale: [255.0, 176.0, 32.0], offset: [0.05, 0.02, 0.001],
+            background: [0, 0, 0, 255] }
+    }
+
+    /// A white phosphor with a slightly cool tint.
+    pub fn white() -> ColorMap {
+        ColorMap { scale: [235.0, 245.0, 255.0], offset: [0.03, 0.03, 0.03],
+            background: [0, 0, 0, 255] }
+    }
+
+    /// This map over a transparent background, and with no tint for the unlit
+    /// phosphor, so that pixels with no glow have zero alpha.
+    pub fn transparent(self) -> ColorMap {
+        ColorMap { offset: [0.0; 3], background: [0; 4], ..self }
+    }
+}
+
+impl Default for ColorMap {
+    fn default() -> ColorMap {
+        ColorMap::green()
+    }
+}
+
+/// Statistics of the samples within the measurement window, over one sweep.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MeasurementStats {
+    pub n_samples: usize,
+    pub mean: f32,
+    pub rms: f32,
+    pub min: f32,
+    pub max: f32,
+}
+
+// A horizontal gate for measurements, with accumulators for the current sweep.
+struct MeasurementWindow {
+    start: f32,
+    end: f32,
+    n: usize,
+    sum: f32,
+    sum_sq: f32,
+    min: f32,
+    max: f32,
+    last: Option<MeasurementStats>,
+}
+
+impl MeasurementWindow {
+    fn new(start: f32, end: f32) -> MeasurementWindow {
+        MeasurementWindow { start, end, n: 0, sum: 0.0, sum_sq: 0.0,
+            min: ::std::f32::INFINITY, max: ::std::f32::NEG_INFINITY, last: None }
+    }
+
+    fn add(&mut self, horiz: f32, sample: f32) {
+        if horiz >= self.start && horiz < self.end {
+            self.n += 1;
+            self.sum += sample;
+            self.sum_sq += sample * sample;
+            self.min = self.min.min(sample);
+            self.max = self.max.max(sample);
+        }
+    }
+
+    // Publish the stats for the sweep just completed, and reset.
+    fn finish_sweep(&mut self) {
+        if self.n > 0 {
+            let n_recip = (self.n as f32).recip();
+            self.last = Some(MeasurementStats {
+                n_samples: self.n,
+                mean: self.sum * n_recip,
+                rms: (self.sum_sq * n_recip).sqrt(),
+                min: self.min,
+                max: self.max,
+            });
+        }
+        *self = MeasurementWindow { last: self.last, ..MeasurementWindow::new(self.start, self.end) };
+    }
+}
+
+/// The direction in which the signal must cross the trigger level.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Slope {
+    Rising,
+    Falling,
+}
+
+/// The condition for starting a sweep.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Trigger {
+    /// The level the signal must cross.
+    pub level: f32,
+    pub slope: Slope,
+    /// The minimum number of samples between one trigger and the next.
+    pub holdoff_samples: usize,
+}
+
+impl Default for Trigger {
+    fn default() -> Trigger {
+        Trigger { level: 0.0, slope: Slope::Rising, holdoff_samples: 0 }
+    }
+}
+
+impl Trigger {
+    fn fires(&self, old: f32, new: f32) -> bool {
+        match self.slope {
+            Slope::Rising => old < self.level && new > self.level,
+            Slope::Falling => old > self.level && new < self.level,
+        }
+    }
 }
 
 enum ScopeState {
@@ -58,39 +338,407 @@ enum ScopeState {
 impl Scope {
     // Create a new Scope instance of the given size.
     pub fn new(width: usize, height: usize) -> Scope {
+        Scope::new_multi(width, height, 1)
+    }
+
+    /// Create a scope showing `channels` traces, each with its own glow.
+    /// Channel 0 is drawn by `provide_samples`, and sets the timebase for the
+    /// others, which are drawn by `provide_samples_channel`.
+    pub fn new_multi(width: usize, height: usize, channels: usize) -> Scope {
+        assert!(channels > 0, "a scope needs at least one channel");
         let glow = vec![0.0; width * height];
         let tc = 1_500.0;
+        let sample_rate = 44_100.0;
+        let persistence_secs = None;
         let sweep = 0.002;
         let horiz = 0.0;
         let gain = 1.0;
         let xylast = None;
         let state = ScopeState::WaitingForTrigger(-1.0);
-        Scope { width, height, glow, tc, sweep, horiz, gain, xylast, state }
+        let trigger = Some(Trigger::default());
+        let trigger_wait = 0;
+        let eye_phase = 0;
+        let blend_mode = BlendMode::Add;
+        let auto_intensity = false;
+        let n_samples = 0;
+        let last_trigger = None;
+        let clip_factor = DEFAULT_CLIP_FACTOR;
+        let mirror = MirrorMode::None;
+        let measurement = None;
+        let density = vec![0.0; height];
+        let channel_scales = vec![(1.0, 0.0); channels];
+        let vertical_offset = 0.0;
+        let vignette = 0.0;
+        let sweep_completed = false;
+        let envelope_fill = false;
+        let envelope_column = None;
+        let intensity_lut = None;
+        let channels = (1..channels)
+            .map(|_| ChannelGlow { glow: vec![0.0; width * height], xylast: None })
+            .collect();
+        let sweep_positions = Vec::new();
+        let auto_gain = false;
+        let peak = 0.0;
+        let effective_gain = gain;
+        Scope { width, height, glow, tc, sample_rate, persistence_secs, sweep, horiz, gain, xylast, state, trigger,
+            trigger_wait, eye_phase, blend_mode, auto_intensity, n_samples, last_trigger, clip_factor, mirror,
+            measurement, density, channel_scales, vertical_offset, vignette,
+            sweep_completed, envelope_fill, envelope_column, intensity_lut, channels,
+            sweep_positions, auto_gain, peak, effective_gain }
+    }
+
+    /// The raw glow intensities of channel 0, one per pixel in row-major order: the pixel
+    /// at column `i` and row `j` (from the top) is at `j * width + i`.
+    pub fn glow(&self) -> &[f32] {
+        &self.glow
+    }
+
+    /// The size of the display, as `(width, height)`.
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Change the size of the display, keeping the settings and the sweep
+    /// position. The existing glow is resampled (nearest neighbor) to the new
+    /// size; the density histogram is cleared. Panics if either dimension is
+    /// zero.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        assert!(width > 0 && height > 0, "scope dimensions must be nonzero");
+        self.glow = self.resampled(&self.glow, width, height);
+        for c in 0..self.channels.len() {
+            let glow = self.resampled(&self.channels[c].glow, width, height);
+            self.channels[c] = ChannelGlow { glow, xylast: None };
+        }
+        self.width = width;
+        self.height = height;
+        self.density = vec![0.0; height];
+        // pixel coordinates from before no longer apply
+        self.xylast = None;
+        self.sweep_positions.clear();
+        self.envelope_column = None;
+    }
+
+    // A glow buffer of this scope's size, resampled to a new size.
+    fn resampled(&self, glow: &[f32], width: usize, height: usize) -> Vec<f32> {
+        let mut result = vec![0.0; width * height];
+        for j in 0..height {
+            let old_j = j * self.height / height;
+            for i in 0..width {
+                let old_i = i * self.width / width;
+                result[j * width + i] = glow[self.idx(old_i, old_j)];
+            }
+        }
+        result
+    }
+
+    /// The current horizontal position of the beam, as a fraction of the width.
+    /// This can exceed 1.0 at the end of a sweep, while waiting for a trigger.
+    pub fn horiz(&self) -> f32 {
+        self.horiz
+    }
+
+    /// Whether a sweep is in progress (as opposed to waiting for a trigger).
+    pub fn is_triggered(&self) -> bool {
+        match self.state {
+            ScopeState::Scanning => true,
+            ScopeState::WaitingForTrigger(_) => false,
+        }
+    }
+
+    /// Progress through the current sweep, from 0.0 to 1.0.
+    pub fn current_sweep_progress(&self) -> f32 {
+        self.horiz.max(0.0).min(1.0)
+    }
+
+    /// The index of the sample (counting all samples given to
+    /// `provide_samples`) at which the trigger last fired, if it has.
+    pub fn last_trigger_sample(&self) -> Option<u64> {
+        self.last_trigger
+    }
+
+    /// Whether a sweep has completed since the last call, for syncing frame
+    /// rendering to sweeps. Reading the flag clears it.
+    pub fn take_sweep_completed(&mut self) -> bool {
+        let completed = self.sweep_completed;
+        self.sweep_completed = false;
+        completed
+    }
+
+    /// Set the vertical gain, where 1.0 maps the range -1..1 to the full
+    /// height. Values below a small positive minimum are clamped to it.
+    pub fn set_gain(&mut self, gain: f32) {
+        self.gain = gain.max(MIN_GAIN);
+        self.effective_gain = self.gain;
+    }
+
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Set the sweep rate, as a fraction of the width per sample. It is
+    /// clamped to a positive range no faster than the width per sample.
+    pub fn set_sweep(&mut self, sweep: f32) {
+        self.sweep = sweep.max(MIN_SWEEP).min(MAX_SWEEP);
+    }
+
+    pub fn sweep(&self) -> f32 {
+        self.sweep
+    }
+
+    /// Set the time constant for the glow to fade, in samples. It is clamped
+    /// to at least one sample.
+    ///
+    /// The decay is applied per sample, so it doesn't depend on the size of
+    /// the blocks given to `provide_samples`.
+    pub fn set_persistence(&mut self, tc_samples: f32) {
+        self.tc = tc_samples.max(MIN_TC);
+        self.persistence_secs = None;
+    }
+
+    /// The time constant for the glow to fade, in samples.
+    pub fn persistence(&self) -> f32 {
+        self.tc
+    }
+
+    /// Set the time constant for the glow to fade, in seconds. It follows
+    /// later changes to the sample rate.
+    pub fn set_persistence_secs(&mut self, secs: f32) {
+        self.tc = (secs * self.sample_rate).max(MIN_TC);
+        self.persistence_secs = Some(secs);
+    }
+
+    /// Set the sample rate of the signal, for converting persistence in
+    /// seconds to samples. The default is 44100.
+    pub fn set_sample_rate(&mut self, sr: f32) {
+        assert!(sr > 0.0, "sample rate must be positive");
+        self.sample_rate = sr;
+        if let Some(secs) = self.persistence_secs {
+            self.set_persistence_secs(secs);
+        }
+    }
+
+    /// Set the condition for starting a sweep. The default is a rising
+    /// crossing of zero.
+    ///
+    /// If no trigger fires within a sweep's worth of samples after the last
+    /// sweep ended, the scope starts one anyway, so the trace stays visible.
+    /// With `None`, each sweep starts as soon as the previous one ends.
+    pub fn set_trigger(&mut self, trigger: Option<Trigger>) {
+        self.trigger = trigger;
+    }
+
+    /// Enable or disable auto gain, which scales the trace so that its peaks
+    /// fill most of the height. The peak amplitude is tracked with an
+    /// instant attack, so transients don't go off screen, and a slow
+    /// release. Quiet signals are boosted by up to 40 dB. The gain set by
+    /// `set_gain` multiplies the auto gain, so 1.0 fills most of the height.
+    /// It applies to the eye, XY and density displays too; in XY mode, the
+    /// peak is the larger of the two axes.
+    pub fn set_auto_gain(&mut self, enabled: bool) {
+        self.auto_gain = enabled;
+        self.effective_gain = self.gain;
+    }
+
+    /// The gain applied to the most recent block, which differs from the
+    /// gain setting when auto gain is on.
+    pub fn effective_gain(&self) -> f32 {
+        self.effective_gain
+    }
+
+    /// Enable or disable automatic intensity compensation for sweep rate.
+    ///
+    /// A fast sweep spreads each sample over more pixels, so the trace gets
+    /// dimmer. When enabled, the intensity is scaled in proportion to the sweep
+    /// rate (relative to the default) so the brightness stays roughly constant.
+    pub fn set_auto_intensity(&mut self, enabled: bool) {
+        self.auto_intensity = enabled;
+    }
+
+    /// Set how dots and lines are combined with the existing glow.
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.blend_mode = mode;
+    }
+
+    /// Set the extent of the gaussian beam, as a multiple of radius.
+    ///
+    /// A smaller factor is faster and gives harder edges, suitable for thin
+    /// crisp traces; a larger one captures more of a soft glow. The default
+    /// is 2.5. Panics if `factor` is not positive.
+    pub fn set_clip_factor(&mut self, factor: f32) {
+        assert!(factor > 0.0, "clip factor must be positive");
+        self.clip_factor = factor;
+    }
+
+    /// Set the reflection applied to the display. This only affects the
+    /// output of `as_rgba`; the glow itself is unchanged.
+    pub fn set_mirror(&mut self, mode: MirrorMode) {
+        self.mirror = mode;
+    }
+
+    /// Gate measurements to the horizontal span from `start_frac` to
+    /// `end_frac` (fractions of the width). Statistics of the samples landing
+    /// in the span are gathered over each sweep, and the boundaries are drawn
+    /// as faint lines.
+    pub fn set_measurement_window(&mut self, start_frac: f32, end_frac: f32) {
+        assert!(0.0 <= start_frac && start_frac < end_frac && end_frac <= 1.0,
+            "invalid measurement window");
+        self.measurement = Some(MeasurementWindow::new(start_frac, end_frac));
+    }
+
+    /// Remove the measurement window.
+    pub fn clear_measurement_window(&mut self) {
+        self.measurement = None;
+    }
+
+    /// Statistics for the measurement window from the last completed sweep.
+    pub fn measurement_stats(&self) -> Option<MeasurementStats> {
+        self.measurement.as_ref().and_then(|m| m.last)
+    }
+
+    /// The RMS level within the measurement window over the last completed sweep.
+    pub fn measurement_rms(&self) -> Option<f32> {
+        self.measurement_stats().map(|stats| stats.rms)
+    }
+
+    /// Set the vertical scale and position of a channel's trace. The gain
+    /// multiplies the scope's gain, and the offset moves the trace's zero line
+    /// up from the center, as a fraction of the height. Panics if the scope
+    /// doesn't have the channel.
+    pub fn set_channel_scale(&mut self, channel: usize, gain: f32, y_offset_frac: f32) {
+        assert!(channel < self.channel_scales.len(), "no channel {}", channel);
+        self.channel_scales[channel] = (gain, y_offset_frac);
+    }
+
+    /// Move the zero line up from the center by `offset_frac` of the height
+    /// (down, if negative), for example to fit a unipolar signal or one with
+    /// a DC offset.
+    pub fn set_vertical_offset(&mut self, offset_frac: f32) {
+        self.vertical_offset = offset_frac;
+    }
+
+    /// Darken the display toward the edges, like the falloff of a CRT. At
+    /// strength 1.0 the corners are black; 0.0 (the default) disables it.
+    /// This only affects the output of `as_rgba`.
+    pub fn set_vignette(&mut self, strength: f32) {
+        self.vignette = strength.max(0.0);
+    }
+
+    /// Draw the envelope of the signal rather than the trace: in each column,
+    /// `provide_samples` fills the area between the minimum and maximum of
+    /// the samples landing in that column. This shows amplitude modulated or
+    /// noisy signals at a glance.
+    pub fn set_envelope_fill(&mut self, enabled: bool) {
+        self.envelope_fill = enabled;
+        self.envelope_column = None;
+    }
+
+    /// Set a custom transfer curve from glow to brightness for `as_rgba`.
+    ///
+    /// The table spans glow values from 0.0 to 1.0 (higher values use the
+    /// last entry), and is linearly interpolated. Brightness 1.0 is full
+    /// intensity of the trace color. An empty table restores the default
+    /// curve.
+    pub fn set_intensity_lut(&mut self, lut: Vec<f32>) {
+        self.intensity_lut = if lut.is_empty() { None } else { Some(lut) };
     }
 
     // Add a dot to the glow.
     pub fn add_dot(&mut self, x: f32, y: f32, r: f32, amp: f32) {
+        if is_x86_feature_detected!("avx") {
+            unsafe { self.add_dot_avx(x, y, r, amp); }
+        } else {
+            self.add_dot_scalar(x, y, r, amp);
+        }
+    }
+
+    fn add_dot_scalar(&mut self, x: f32, y: f32, r: f32, amp: f32) {
         let r_recip = r.recip();
-        let i0 = ((x - CLIP_FACTOR * r).ceil().max(0.0) as usize).min(self.width);
-        let i1 = ((x + CLIP_FACTOR * r).ceil().max(0.0) as usize).min(self.width);
-        let j0 = ((y - CLIP_FACTOR * r).ceil().max(0.0) as usize).min(self.height);
-        let j1 = ((y + CLIP_FACTOR * r).ceil().max(0.0) as usize).min(self.height);
+        let (i0, i1, j0, j1) = self.dot_bounds(x, y, r);
+        // The gaussian is separable, so for small dots compute the horizontal
+        // factors once rather than per row.
+        let mut zxs = [0.0f32; MAX_DOT_COLS];
+        let cache = i1 - i0 <= MAX_DOT_COLS;
+        if cache {
+            for i in i0..i1 {
+                zxs[i - i0] = gauss_approx(r_recip * (i as f32 - x));
+            }
+        }
         for j in j0..j1 {
             let zy_amp = gauss_approx(r_recip * (j as f32 - y)) * amp;
             for i in i0..i1 {
-                let zx = gauss_approx(r_recip * (i as f32 - x));
-                self.glow[j * self.width + i] += zx * zy_amp;
+                let zx = if cache { zxs[i - i0] } else { gauss_approx(r_recip * (i as f32 - x)) };
+                let ix = self.idx(i, j);
+                self.blend(ix, zx * zy_amp);
+            }
+        }
+    }
+
+    // The range of pixels covered by a dot, clipped to the display.
+    fn dot_bounds(&self, x: f32, y: f32, r: f32) -> (usize, usize, usize, usize) {
+        let i0 = ((x - self.clip_factor * r).ceil().max(0.0) as usize).min(self.width);
+        let i1 = ((x + self.clip_factor * r).ceil().max(0.0) as usize).min(self.width);
+        let j0 = ((y - self.clip_factor * r).ceil().max(0.0) as usize).min(self.height);
+        let j1 = ((y + self.clip_factor * r).ceil().max(0.0) as usize).min(self.height);
+        (i0, i1, j0, j1)
+    }
+
+    // Same as `add_dot_scalar`, 8 pixels of a row at a time.
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[target_feature(enable = "avx")]
+    unsafe fn add_dot_avx(&mut self, x: f32, y: f32, r: f32, amp: f32) {
+        let r_recip = r.recip();
+        let (i0, i1, j0, j1) = self.dot_bounds(x, y, r);
+        let n8 = (i1 - i0) / 8 * 8;
+        let steps = _mm256_set_ps(7.0, 6.0, 5.0, 4.0, 3.0, 2.0, 1.0, 0.0);
+        for j in j0..j1 {
+            let zy_amp = gauss_approx(r_recip * (j as f32 - y)) * amp;
+            for k in (0..n8).step_by(8) {
+                let di = _mm256_add_ps(steps, _mm256_set1_ps((i0 + k) as f32 - x));
+                let zx = avx_gauss_approx(_mm256_mul_ps(di, _mm256_set1_ps(r_recip)));
+                let z = _mm256_mul_ps(zx, _mm256_set1_ps(zy_amp));
+                let ix = self.idx(i0 + k, j);
+                self.blend_avx(ix, z);
+            }
+            for i in i0 + n8..i1 {
+                let z = gauss_approx(r_recip * (i as f32 - x)) * zy_amp;
+                let ix = self.idx(i, j);
+                self.blend(ix, z);
             }
         }
     }
 
+    /// Add a dot to the glow, with the center given in coordinates normalized
+    /// to the screen size (0.0 to 1.0 on each axis). The radius is in pixels.
+    pub fn add_dot_norm(&mut self, x: f32, y: f32, r: f32, amp: f32) {
+        let (x, y) = self.denorm(x, y);
+        self.add_dot(x, y, r, amp);
+    }
+
+    /// Add a line to the glow, with the endpoints given in normalized coordinates.
+    /// The radius is in pixels.
+    pub fn add_line_norm(&mut self, x0: f32, y0: f32, x1: f32, y1: f32, r: f32, amp: f32) {
+        let (x0, y0) = self.denorm(x0, y0);
+        let (x1, y1) = self.denorm(x1, y1);
+        self.add_line(x0, y0, x1, y1, r, amp);
+    }
+
+    // Convert normalized screen coordinates to pixels.
+    fn denorm(&self, x: f32, y: f32) -> (f32, f32) {
+        (x * self.width as f32, y * self.height as f32)
+    }
+
+    /// Add a line to the glow by stepping dots along it. The number of dots is
+    /// chosen so that they are spaced at most `STEP_SPACING` radii apart.
     pub fn add_line_step(&mut self, x0: f32, y0: f32, x1: f32, y1: f32, r: f32, amp: f32) {
-        let n = 20;
+        let dx = x1 - x0;
+        let dy = y1 - y0;
+        let len = (dx * dx + dy * dy).sqrt();
+        let n = ((len / (r * STEP_SPACING)).ceil() as usize).max(1);
         let step = (n as f32).recip();
         let amp = amp / (n as f32);
         for i in 0..n {
             let t = (i as f32 + 0.5) * step;
-            self.add_dot(x0 + (x1 - x0) * t, y0 + (y1 - y0) * t, r, amp);
+            self.add_dot(x0 + dx * t, y0 + dy * t, r, amp);
         }
     }
 
@@ -100,9 +748,19 @@ impl Scope {
         let len2 = dx * dx + dy * dy;
         if len2 < 1.0 {
             self.add_dot((x0 + x1) * 0.5, (y0 + y1) * 0.5, r, amp);
-            return;
+        } else if len2 < (LINE_STEP_CROSSOVER * r).powi(2) {
+            self.add_line_step(x0, y0, x1, y1, r, amp);
+        } else {
+            self.add_line_analytic(x0, y0, x1, y1, r, amp);
         }
-        // Also, for medium-small lengths, add_line_step with 2 steps might win.
+    }
+
+    /// Add a line to the glow, computing the convolution of the line segment
+    /// with the gaussian analytically.
+    pub fn add_line_analytic(&mut self, x0: f32, y0: f32, x1: f32, y1: f32, r: f32, amp: f32) {
+        let dx = x1 - x0;
+        let dy = y1 - y0;
+        let len2 = dx * dx + dy * dy;
         let uvscale = 1.0 / (r * len2.sqrt());
         let vx = -dy * uvscale;
         let vy = dx * uvscale;
@@ -114,35 +772,78 @@ impl Scope {
         let v0 = -x0 * vx - y0 * vy;
         let ustep = dx * ux + dy * uy;
         let amp = r / uscale * amp / len2.sqrt();
-        let i0 = ((x0.min(x1) - CLIP_FACTOR * r).ceil().max(0.0) as usize).min(self.width);
-        let i1 = ((x0.max(x1) + CLIP_FACTOR * r).ceil().max(0.0) as usize).min(self.width);
-        let j0 = ((y0.min(y1) - CLIP_FACTOR * r).ceil().max(0.0) as usize).min(self.height);
-        let j1 = ((y0.max(y1) + CLIP_FACTOR * r).ceil().max(0.0) as usize).min(self.height);
-        // TODO: (i1-i0).min(j1-j0) is a measure of wastefulness of drawing the whole rect.
-        // If this is high, compute horiz bounds per scan line.
+        let i0 = ((x0.min(x1) - self.clip_factor * r).ceil().max(0.0) as usize).min(self.width);
+        let i1 = ((x0.max(x1) + self.clip_factor * r).ceil().max(0.0) as usize).min(self.width);
+        let j0 = ((y0.min(y1) - self.clip_factor * r).ceil().max(0.0) as usize).min(self.height);
+        let j1 = ((y0.max(y1) + self.clip_factor * r).ceil().max(0.0) as usize).min(self.height);
+        // Within the bounding rect, only visit pixels within the clip distance
+        // of the line, solving |vx * i + vy * j + v0| <= clip_factor for i.
+        let vx_recip = vx.recip();
         for j in j0..j1 {
-            for i in i0..i1 {
+            let (lo, hi) = if vx == 0.0 {
+                (i0, i1)
+            } else {
+                let vj = vy * (j as f32) + v0;
+                let a = (-self.clip_factor - vj) * vx_recip;
+                let b = (self.clip_factor - vj) * vx_recip;
+                let lo = (a.min(b).ceil().max(0.0) as usize).max(i0).min(i1);
+                let hi = ((a.max(b).floor() + 1.0).max(0.0) as usize).max(lo).min(i1);
+                (lo, hi)
+            };
+            for i in lo..hi {
                 let u = ux * (i as f32) + uy * (j as f32) + u0;
                 let v = vx * (i as f32) + vy * (j as f32) + v0;
                 let z = amp * gauss_approx(v) * (erf_approx(u) - erf_approx(u - ustep));
-                self.glow[j * self.width + i] += z;
+                let ix = self.idx(i, j);
+                self.blend(ix, z);
             }
         }
     }
 
+    // Index into the glow, checking the coordinates in debug builds.
+    #[inline]
+    fn idx(&self, i: usize, j: usize) -> usize {
+        debug_assert!(i < self.width && j < self.height,
+            "glow coordinates ({}, {}) out of range", i, j);
+        j * self.width + i
+    }
+
+    #[inline]
+    fn blend(&mut self, ix: usize, z: f32) {
+        match self.blend_mode {
+            BlendMode::Add => self.glow[ix] += z,
+            BlendMode::Max => self.glow[ix] = self.glow[ix].max(z),
+        }
+    }
+
+    // `blend` for the 8 pixels starting at `ix`, which must be in one row.
     #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
     #[target_feature(enable = "avx")]
-    unsafe fn as_rgba_body_avx(&self, im: &mut [u8]) {
+    unsafe fn blend_avx(&mut self, ix: usize, z: __m256) {
+        assert!(ix % self.width + 8 <= self.width && ix < self.glow.len(),
+            "glow index {} out of range for 8 pixels", ix);
+        let p = self.glow.as_mut_ptr().add(ix);
+        let g = _mm256_loadu_ps(p);
+        let g = match self.blend_mode {
+            BlendMode::Add => _mm256_add_ps(g, z),
+            BlendMode::Max => _mm256_max_ps(g, z),
+        };
+        _mm256_storeu_ps(p, g);
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[target_feature(enable = "avx")]
+    unsafe fn as_rgba_body_avx(&self, glow: &[f32], im: &mut [u8]) {
         let n = self.width * self.height;
         assert!(n % 8 == 0);
-        assert!(n == self.glow.len());
+        assert!(n == glow.len());
         assert!(n * 4 == im.len());
         let avx = AvxF32::create();
         let shuf = _mm256_set_epi8(
             15, 11, 7, 3, 14, 10, 6, 2, 13, 9, 5, 1, 12, 8, 4, 0,
             15, 11, 7, 3, 14, 10, 6, 2, 13, 9, 5, 1, 12, 8, 4, 0);
         for i in (0..n).step_by(8) {
-            let x = avx.from_slice(&self.glow[i..]);
+            let x = avx.from_slice(&glow[i..]);
             let r = avx_sqrt_pos11(x + 0.001) * 64.0;
             let g = avx_sqrt_pos11(x + 0.05) * 255.0;
             let b = avx_sqrt_pos11(x + 0.13) * 224.0;
@@ -159,27 +860,173 @@ impl Scope {
     }
 
     pub fn as_rgba(&self) -> Vec<u8> {
+        self.as_rgba_with(&ColorMap::default())
+    }
+
+    /// Render the display to RGBA pixels with the given color mapping.
+    pub fn as_rgba_with(&self, map: &ColorMap) -> Vec<u8> {
         let n = self.width * self.height;
         let mut im = vec![255; n * 4];
-        if is_x86_feature_detected!("avx") {
-            unsafe { self.as_rgba_body_avx(&mut im); }
+        let vignetted;
+        let glow = if self.vignette > 0.0 {
+            vignetted = self.vignetted(&self.glow);
+            &vignetted
+        } else {
+            &self.glow
+        };
+        if let Some(ref lut) = self.intensity_lut {
+            for i in 0..n {
+                let v = lut_sample(lut, glow[i]);
+                let beam = [v * map.scale[0], v * map.scale[1], v * map.scale[2]];
+                put_pixel(&mut im[i * 4..i * 4 + 4], map.background, beam);
+            }
+        } else if *map == ColorMap::green() && is_x86_feature_detected!("avx") {
+            unsafe { self.as_rgba_body_avx(glow, &mut im); }
         } else {
             // TODO: lut is probably faster scalar fallback
             for i in 0..n {
-                let x = self.glow[i];
-                let r = ((x + 0.001).sqrt() * 64.0).min(255.0) as u8;
-                let g = ((x + 0.05).sqrt() * 255.0).min(255.0) as u8;
-                let b = ((x + 0.13).sqrt() * 224.0).min(255.0) as u8;
-                im[i * 4 + 0] = r;
-                im[i * 4 + 1] = g;
-                im[i * 4 + 2] = b;
+                let x = glow[i];
+                let mut beam = [0.0; 3];
+                for c in 0..3 {
+                    beam[c] = (x + map.offset[c]).sqrt() * map.scale[c];
+                }
+                put_pixel(&mut im[i * 4..i * 4 + 4], map.background, beam);
+            }
+        }
+        for (c, channel) in self.channels.iter().enumerate() {
+            let tint = CHANNEL_TINTS[c % CHANNEL_TINTS.len()];
+            let vignetted;
+            let glow = if self.vignette > 0.0 {
+                vignetted = self.vignetted(&channel.glow);
+                &vignetted
+            } else {
+                &channel.glow
+            };
+            for i in 0..n {
+                let v = glow[i].max(0.0).sqrt();
+                for k in 0..3 {
+                    let x = im[i * 4 + k] as f32 + v * tint[k];
+                    im[i * 4 + k] = x.min(255.0) as u8;
+                }
             }
         }
         self.render_grid_lines(&mut im);
+        if let Some(ref m) = self.measurement {
+            for &frac in &[m.start, m.end] {
+                let x = ((frac * self.width as f32) as usize).min(self.width - 1);
+                self.render_vline(x, 0, self.height, &mut im);
+            }
+        }
+        self.apply_mirror(&mut im);
         im
     }
 
+    // The glow darkened toward the edges by the vignette mask, which falls off
+    // with the square of the distance from the center (normalized to 1 at the
+    // corners).
+    fn vignetted(&self, glow: &[f32]) -> Vec<f32> {
+        let cx = 0.5 * (self.width as f32 - 1.0);
+        let cy = 0.5 * (self.height as f32 - 1.0);
+        let scale = (cx * cx + cy * cy).max(1.0).recip();
+        let mut glow = glow.to_vec();
+        for j in 0..self.height {
+            let dy = j as f32 - cy;
+            for i in 0..self.width {
+                let dx = i as f32 - cx;
+                let mask = (1.0 - self.vignette * (dx * dx + dy * dy) * scale).max(0.0);
+                glow[self.idx(i, j)] *= mask;
+            }
+        }
+        glow
+    }
+
+    // Reflect the rendered image according to the mirror mode.
+    fn apply_mirror(&self, im: &mut [u8]) {
+        let stride = self.width * 4;
+        if self.mirror == MirrorMode::Horizontal || self.mirror == MirrorMode::Both {
+            for row in im.chunks_mut(stride) {
+                for i in self.width - self.width / 2..self.width {
+                    let src = (self.width - 1 - i) * 4;
+                    for k in 0..4 {
+                        row[i * 4 + k] = row[src + k];
+                    }
+                }
+            }
+        }
+        if self.mirror == MirrorMode::Vertical || self.mirror == MirrorMode::Both {
+            for j in self.height - self.height / 2..self.height {
+                let src = (self.height - 1 - j) * stride;
+                let (top, bottom) = im.split_at_mut(j * stride);
+                bottom[..stride].copy_from_slice(&top[src..src + stride]);
+            }
+        }
+    }
+
+    /// Convolve the glow of every channel with a `kw` by `kh` kernel (in
+    /// row-major order), for simulating beam shapes beyond the built-in
+    /// gaussian. The kernel is centered on the pixel at `(kw / 2, kh / 2)`; at
+    /// the borders, the glow is extended by clamping coordinates to the edge.
+    pub fn apply_kernel(&mut self, kernel: &[f32], kw: usize, kh: usize) {
+        assert_eq!(kernel.len(), kw * kh);
+        self.glow = self.convolve(&self.glow, kernel, kw, kh);
+        for c in 0..self.channels.len() {
+            self.channels[c].glow = self.convolve(&self.channels[c].glow, kernel, kw, kh);
+        }
+    }
+
+    fn convolve(&self, glow: &[f32], kernel: &[f32], kw: usize, kh: usize) -> Vec<f32> {
+        let w = self.width as isize;
+        let h = self.height as isize;
+        let mut result = vec![0.0; glow.len()];
+        for j in 0..h {
+            for i in 0..w {
+                let mut sum = 0.0;
+                for kj in 0..kh {
+                    let y = (j + kj as isize - (kh / 2) as isize).max(0).min(h - 1);
+                    let start = self.idx(0, y as usize);
+                    let row = &glow[start..start + self.width];
+                    for ki in 0..kw {
+                        let x = (i + ki as isize - (kw / 2) as isize).max(0).min(w - 1);
+                        sum += kernel[kj * kw + ki] * row[x as usize];
+                    }
+                }
+                result[self.idx(i as usize, j as usize)] = sum;
+            }
+        }
+        result
+    }
+
+    /// Count the glow values of all channels in `bins` equal buckets spanning
+    /// 0 to the maximum glow value. The maximum itself lands in the last
+    /// bucket. If the glow is entirely dark, everything lands in the first
+    /// bucket.
+    pub fn glow_histogram(&self, bins: usize) -> Vec<u32> {
+        let mut hist = vec![0; bins];
+        if bins == 0 {
+            return hist;
+        }
+        let all = || self.glow.iter().chain(self.channels.iter().flat_map(|c| &c.glow));
+        let max = all().cloned().fold(0.0, f32::max);
+        let scale = if max > 0.0 { bins as f32 / max } else { 0.0 };
+        for &x in all() {
+            let bin = ((x * scale).max(0.0) as usize).min(bins - 1);
+            hist[bin] += 1;
+        }
+        hist
+    }
+
+    /// Fade the glow of every channel by `factor`.
     pub fn fade(&mut self, factor: f32) {
+        self.fade_glow(factor);
+        for channel in &mut self.channels {
+            for x in &mut channel.glow {
+                *x *= factor;
+            }
+        }
+    }
+
+    // Fade the glow being drawn, which is the channel currently swapped in.
+    fn fade_glow(&mut self, factor: f32) {
         for x in &mut self.glow {
             *x *= factor;
         }
@@ -187,44 +1034,273 @@ impl Scope {
 
     pub fn provide_samples(&mut self, samples: &[f32]) {
         let factor = (-(samples.len() as f32) / self.tc).exp();
-        self.fade(factor);
+        self.fade_glow(factor);
         let mut amp = 2.0 * factor;
+        if self.auto_intensity {
+            amp *= self.sweep * (1.0 / REFERENCE_SWEEP);
+        }
         let ampgain = (1.0 / self.tc).exp();
-        let y0 = self.height as f32 * 0.5;
-        let yscale = y0 * self.gain;
+        if self.auto_gain {
+            self.update_auto_gain(samples.iter().map(|x| x.abs()));
+        }
+        let (channel_gain, y_offset) = self.channel_scales[0];
+        let y0 = self.height as f32 * (0.5 - self.vertical_offset - y_offset);
+        let yscale = self.height as f32 * 0.5 * self.effective_gain * channel_gain;
+        let record = !self.channels.is_empty();
+        self.sweep_positions.clear();
         for sample in samples {
             match self.state {
                 ScopeState::Scanning => {
+                    if record {
+                        self.sweep_positions.push(Some(self.horiz));
+                    }
                     let x = self.horiz * (self.width as f32);
                     let y = y0 - yscale * sample;
-                    if let Some((xlast, ylast)) = self.xylast {
+                    if self.envelope_fill {
+                        self.add_envelope_sample(x, y, amp);
+                    } else if let Some((xlast, ylast)) = self.xylast {
                         self.add_line(xlast, ylast, x, y, 1.0, amp);
-                        amp *= ampgain;
                     }
                     self.xylast = Some((x, y));
+                    if let Some(ref mut m) = self.measurement {
+                        m.add(self.horiz, *sample);
+                    }
                     self.horiz += self.sweep;
                     if self.horiz > 1.0 {
+                        if let Some(ref mut m) = self.measurement {
+                            m.finish_sweep();
+                        }
+                        self.sweep_completed = true;
+                        self.flush_envelope_column(amp);
                         self.xylast = None;
                         self.state = ScopeState::WaitingForTrigger(*sample);
                     }
                 }
                 ScopeState::WaitingForTrigger(old) => {
-                    let trigger_level = 0.0;
-                    if old < trigger_level && *sample > trigger_level {
+                    let trigger = self.trigger;
+                    let (fired, timed_out) = match trigger {
+                        Some(t) => {
+                            let ready = match self.last_trigger {
+                                Some(last) => self.n_samples - last >= t.holdoff_samples as u64,
+                                None => true,
+                            };
+                            if !ready {
+                                self.trigger_wait = 0;
+                            }
+                            // Free-run if the trigger hasn't fired within the
+                            // length of a sweep.
+                            (ready && t.fires(old, *sample),
+                                ready && self.trigger_wait as f32 * self.sweep >= 1.0)
+                        }
+                        None => (false, true),
+                    };
+                    if record {
+                        self.sweep_positions.push(if fired || timed_out { Some(0.0) } else { None });
+                    }
+                    if fired || timed_out {
                         self.horiz = 0.0; // TODO: linear interp
                         let x = self.horiz * (self.width as f32);
                         let y = y0 - yscale * sample;
                         self.xylast = Some((x, y));
                         self.state = ScopeState::Scanning;
+                        self.trigger_wait = 0;
+                        if fired {
+                            self.last_trigger = Some(self.n_samples);
+                        }
                     } else {
                         self.state = ScopeState::WaitingForTrigger(*sample);
+                        self.trigger_wait += 1;
                     }
                 }
             }
+            // Every sample advances the decay, drawn or not, so that it
+            // doesn't depend on how the samples are split into blocks.
+            amp *= ampgain;
+            self.n_samples += 1;
+        }
+    }
+
+    // Track the peak over a block, and set the gain for the block so that the
+    // largest peak within it fits.
+    fn update_auto_gain<I: Iterator<Item = f32>>(&mut self, magnitudes: I) {
+        let release = (-1.0 / (AUTO_GAIN_RELEASE * self.sample_rate)).exp();
+        let mut block_peak: f32 = 0.0;
+        for m in magnitudes {
+            self.peak = (self.peak * release).max(m);
+            block_peak = block_peak.max(self.peak);
         }
+        let auto = (AUTO_GAIN_FILL / block_peak).min(AUTO_GAIN_MAX);
+        self.effective_gain = self.gain * auto;
     }
 
-    fn render_grid_lines(&self, im: &mut [u8]) {
+    /// Provide samples for one channel of a multi-channel scope. Channel 0
+    /// is the same as `provide_samples`. The other channels follow its
+    /// sweep, so each block should be given to channel 0 first, then to the
+    /// others; samples beyond the length of channel 0's block are ignored.
+    /// Each channel fades by the number of samples it is given.
+    pub fn provide_samples_channel(&mut self, channel: usize, samples: &[f32]) {
+        if channel == 0 {
+            return self.provide_samples(samples);
+        }
+        assert!(channel <= self.channels.len(), "no channel {}", channel);
+        // Draw into this channel's glow by swapping it in.
+        ::std::mem::swap(&mut self.glow, &mut self.channels[channel - 1].glow);
+        let mut xylast = self.channels[channel - 1].xylast;
+        let factor = (-(samples.len() as f32) / self.tc).exp();
+        self.fade_glow(factor);
+        let mut amp = 2.0 * factor;
+        if self.auto_intensity {
+            amp *= self.sweep * (1.0 / REFERENCE_SWEEP);
+        }
+        let ampgain = (1.0 / self.tc).exp();
+        let (channel_gain, y_offset) = self.channel_scales[channel];
+        let y0 = self.height as f32 * (0.5 - self.vertical_offset - y_offset);
+        let yscale = self.height as f32 * 0.5 * self.effective_gain * channel_gain;
+        for k in 0..samples.len().min(self.sweep_positions.len()) {
+            xylast = match self.sweep_positions[k] {
+                Some(horiz) => {
+                    let x = horiz * (self.width as f32);
+                    let y = y0 - yscale * samples[k];
+                    match xylast {
+                        // a new sweep starts back at the left
+                        Some((xlast, ylast)) if x >= xlast =>
+                            self.add_line(xlast, ylast, x, y, 1.0, amp),
+                        _ => (),
+                    }
+                    Some((x, y))
+                }
+                None => None,
+            };
+            amp *= ampgain;
+        }
+        self.channels[channel - 1].xylast = xylast;
+        ::std::mem::swap(&mut self.glow, &mut self.channels[channel - 1].glow);
+    }
+
+    // Accumulate a sample into the current envelope column, filling the
+    // previous column when the beam moves on.
+    fn add_envelope_sample(&mut self, x: f32, y: f32, amp: f32) {
+        let col = (x.max(0.0) as usize).min(self.width - 1);
+        match self.envelope_column {
+            Some((c, ymin, ymax)) if c == col => {
+                self.envelope_column = Some((c, ymin.min(y), ymax.max(y)));
+                return;
+            }
+            _ => self.flush_envelope_column(amp),
+        }
+        self.envelope_column = Some((col, y, y));
+    }
+
+    fn flush_envelope_column(&mut self, amp: f32) {
+        if let Some((col, ymin, ymax)) = self.envelope_column.take() {
+            let j0 = (ymin.round().max(0.0) as usize).min(self.height);
+            let j1 = ((ymax.round() + 1.0).max(0.0) as usize).min(self.height);
+            for j in j0..j1 {
+                let ix = self.idx(col, j);
+                self.blend(ix, amp);
+            }
+        }
+    }
+
+    /// Provide samples for an eye diagram display. The trace wraps every
+    /// `samples_per_symbol` samples, so that successive symbol periods
+    /// overlay each other and accumulate in the glow.
+    pub fn provide_eye(&mut self, samples: &[f32], samples_per_symbol: usize) {
+        assert!(samples_per_symbol > 0);
+        let factor = (-(samples.len() as f32) / self.tc).exp();
+        self.fade_glow(factor);
+        let mut amp = 2.0 * factor;
+        let ampgain = (1.0 / self.tc).exp();
+        if self.auto_gain {
+            self.update_auto_gain(samples.iter().map(|x| x.abs()));
+        }
+        let y0 = self.height as f32 * 0.5;
+        let yscale = y0 * self.effective_gain;
+        let xscale = self.width as f32 / samples_per_symbol as f32;
+        for sample in samples {
+            let phase = self.eye_phase % samples_per_symbol;
+            let x = phase as f32 * xscale;
+            let y = y0 - yscale * sample;
+            if let Some((xlast, ylast)) = self.xylast {
+                if phase == 0 {
+                    // finish the previous period at the right edge
+                    self.add_line(xlast, ylast, self.width as f32, y, 1.0, amp);
+                } else {
+                    self.add_line(xlast, ylast, x, y, 1.0, amp);
+                }
+                amp *= ampgain;
+            }
+            self.xylast = Some((x, y));
+            self.eye_phase = phase + 1;
+        }
+    }
+
+    /// Provide samples for an XY display, plotting `xs` against `ys` as on an
+    /// analog scope in XY mode. Both axes are scaled by the gain (relative to
+    /// the height, so a circle stays round) around the center. The sweep is
+    /// not used. If the slices differ in length, the extra samples are
+    /// ignored.
+    pub fn provide_samples_xy(&mut self, xs: &[f32], ys: &[f32]) {
+        let n = xs.len().min(ys.len());
+        let factor = (-(n as f32) / self.tc).exp();
+        self.fade_glow(factor);
+        let mut amp = 2.0 * factor;
+        let ampgain = (1.0 / self.tc).exp();
+        let x0 = self.width as f32 * 0.5;
+        if self.auto_gain {
+            self.update_auto_gain(xs.iter().zip(ys).map(|(x, y)| x.abs().max(y.abs())));
+        }
+        let y0 = self.height as f32 * 0.5;
+        let scale = y0 * self.effective_gain;
+        for (sx, sy) in xs.iter().zip(ys) {
+            let x = x0 + scale * sx;
+            let y = y0 - scale * sy;
+            if let Some((xlast, ylast)) = self.xylast {
+                self.add_line(xlast, ylast, x, y, 1.0, amp);
+                amp *= ampgain;
+            }
+            self.xylast = Some((x, y));
+        }
+    }
+
+    /// Accumulate a histogram of sample amplitudes, and display it as a
+    /// distribution: each row is lit from the left edge in proportion to how
+    /// much time the signal spends at that level, relative to the most
+    /// common level. The histogram fades with the persistence time constant.
+    pub fn provide_density(&mut self, samples: &[f32]) {
+        let factor = (-(samples.len() as f32) / self.tc).exp();
+        for d in &mut self.density {
+            *d *= factor;
+        }
+        if self.auto_gain {
+            self.update_auto_gain(samples.iter().map(|x| x.abs()));
+        }
+        let y0 = self.height as f32 * 0.5;
+        let yscale = y0 * self.effective_gain;
+        for sample in samples {
+            let y = (y0 - yscale * sample).floor();
+            if y >= 0.0 && y < self.height as f32 {
+                self.density[y as usize] += 1.0;
+            }
+        }
+        let max = self.density.iter().cloned().fold(0.0, f32::max);
+        let scale = if max > 0.0 { self.width as f32 / max } else { 0.0 };
+        for j in 0..self.height {
+            let len = self.density[j] * scale;
+            let start = self.idx(0, j);
+            let row = &mut self.glow[start..start + self.width];
+            for (i, z) in row.iter_mut().enumerate() {
+                *z = (len - i as f32).max(0.0).min(1.0);
+            }
+        }
+    }
+
+    /// Draw the graticule onto an RGBA image of the scope's dimensions, by
+    /// halving the color of the pixels under it. `as_rgba` applies this; it's
+    /// exposed for renderers built on `glow`. Panics if the image is the
+    /// wrong size.
+    pub fn render_grid_lines(&self, im: &mut [u8]) {
+        assert_eq!(im.len(), self.width * self.height * 4, "image size doesn't match scope");
         let x2 = self.width / 2;
         let y2 = self.height / 2;
         let grid_sp = 60;
@@ -275,6 +1351,28 @@ pub fn gauss_approx(x: f32) -> f32 {
     (1.0 + y * y).recip()
 }
 
+// Set an RGBA pixel to the beam color added to the background.
+fn put_pixel(pixel: &mut [u8], background: [u8; 4], beam: [f32; 3]) {
+    let mut alpha = background[3];
+    for c in 0..3 {
+        let x = (background[c] as f32 + beam[c]).max(0.0).min(255.0) as u8;
+        pixel[c] = x;
+        alpha = alpha.max(beam[c].max(0.0).min(255.0) as u8);
+    }
+    pixel[3] = alpha;
+}
+
+// Sample a lookup table spanning 0.0 to 1.0, with linear interpolation.
+fn lut_sample(lut: &[f32], x: f32) -> f32 {
+    let pos = x.max(0.0).min(1.0) * (lut.len() - 1) as f32;
+    let i = pos as usize;
+    if i + 1 >= lut.len() {
+        return lut[lut.len() - 1];
+    }
+    let frac = pos - i as f32;
+    lut[i] + frac * (lut[i + 1] - lut[i])
+}
+
 // Approximate erf(x * sqrt(pi) / 2); approx 1.6e-3 error
 pub fn erf_approx(x: f32) -> f32 {
     let xx = x * x;
@@ -282,14 +1380,786 @@ pub fn erf_approx(x: f32) -> f32 {
     x / (1.0 + x * x).sqrt()
 }
 
+// `gauss_approx` for 8 values at once.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[target_feature(enable = "avx")]
+unsafe fn avx_gauss_approx(x: __m256) -> __m256 {
+    let xx = _mm256_mul_ps(x, x);
+    let poly = _mm256_add_ps(_mm256_set1_ps(0.215), _mm256_mul_ps(_mm256_set1_ps(0.0952), xx));
+    let y = _mm256_add_ps(x, _mm256_mul_ps(poly, _mm256_mul_ps(x, xx)));
+    let one = _mm256_set1_ps(1.0);
+    _mm256_div_ps(one, _mm256_add_ps(one, _mm256_mul_ps(y, y)))
+}
+
 fn avx_sqrt_pos11<S: SimdF32>(x: S) -> S {
     x * x.rsqrt11()
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    // Index of the brightest pixel in the glow.
+    fn argmax(scope: &Scope) -> (usize, usize) {
+        let mut best = 0;
+        for i in 0..scope.glow.len() {
+            if scope.glow[i] > scope.glow[best] {
+                best = i;
+            }
+        }
+        (best % scope.width, best / scope.width)
+    }
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn dot_norm_center() {
+        for &(w, h) in &[(640, 480), (100, 60), (32, 256)] {
+            let mut scope = Scope::new(w, h);
+            scope.add_dot_norm(0.5, 0.5, 1.0, 1.0);
+            assert_eq!(argmax(&scope), (w / 2, h / 2));
+        }
+    }
+
+    #[test]
+    fn max_blend_doesnt_accumulate() {
+        let mut single = Scope::new(64, 64);
+        single.add_dot(32.0, 32.0, 2.0, 1.0);
+        let mut add = Scope::new(64, 64);
+        let mut max = Scope::new(64, 64);
+        max.set_blend_mode(BlendMode::Max);
+        for _ in 0..3 {
+            add.add_dot(32.0, 32.0, 2.0, 1.0);
+            max.add_dot(32.0, 32.0, 2.0, 1.0);
+        }
+        for i in 0..single.glow.len() {
+            assert_eq!(max.glow[i], single.glow[i]);
+            assert!((add.glow[i] - 3.0 * single.glow[i]).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn clip_factor_extent() {
+        // Width in pixels of the lit row through the center of a dot.
+        let extent = |factor: f32| {
+            let mut scope = Scope::new(64, 64);
+            scope.set_clip_factor(factor);
+            scope.add_dot(32.0, 32.0, 2.0, 1.0);
+            scope.glow[32 * 64..33 * 64].iter().filter(|&&z| z > 0.0).count()
+        };
+        assert_eq!(extent(2.5), 10);
+        assert_eq!(extent(1.0), 4);
+        assert_eq!(extent(5.0), 20);
+    }
+
+    #[test]
+    #[should_panic]
+    fn clip_factor_positive() {
+        Scope::new(64, 64).set_clip_factor(0.0);
+    }
+
+    #[test]
+    fn horizontal_mirror() {
+        let (w, h) = (64, 32);
+        let mut scope = Scope::new(w, h);
+        scope.set_mirror(MirrorMode::Horizontal);
+        scope.add_line(3.0, 5.0, 20.0, 25.0, 1.5, 1.0);
+        let im = scope.as_rgba();
+        let stride = w * 4;
+        for j in 0..h {
+            for i in 0..w {
+                let a = j * stride + i * 4;
+                let b = j * stride + (w - 1 - i) * 4;
+                assert_eq!(&im[a..a + 4], &im[b..b + 4]);
+            }
+        }
+        // The original trace is still there.
+        let dark = Scope::new(w, h).as_rgba();
+        let ix = 5 * stride + 3 * 4;
+        assert!(im[ix + 1] > dark[ix + 1]);
+    }
+
+    #[test]
+    fn measurement_window_rms() {
+        // One sweep is 500 samples; the level steps up before the window.
+        let mut samples = vec![-1.0];
+        samples.extend((0..600).map(|i| if i < 220 { 0.25 } else { 1.0 }));
+        let mut scope = Scope::new(64, 64);
+        scope.set_measurement_window(0.5, 1.0);
+        scope.provide_samples(&samples[..400]);
+        assert_eq!(scope.measurement_rms(), None);
+        scope.provide_samples(&samples[400..]);
+        let stats = scope.measurement_stats().unwrap();
+        // give or take one for rounding in the accumulated position
+        assert!(stats.n_samples >= 249 && stats.n_samples <= 251);
+        assert!((stats.rms - 1.0).abs() < 1e-6);
+        assert_eq!((stats.min, stats.max), (1.0, 1.0));
+
+        let mut scope = Scope::new(64, 64);
+        scope.set_measurement_window(0.0, 0.4);
+        scope.provide_samples(&samples);
+        assert!((scope.measurement_rms().unwrap() - 0.25).abs() < 1e-6);
+    }
+
+    // Length of the lit bar in each row after `provide_density`.
+    fn density_bars(samples: &[f32]) -> Vec<usize> {
+        let (w, h) = (100, 64);
+        let mut scope = Scope::new(w, h);
+        scope.provide_density(samples);
+        (0..h).map(|j| scope.glow[j * w..(j + 1) * w].iter().filter(|&&z| z > 0.5).count())
+            .collect()
+    }
+
+    #[test]
+    fn density_shapes() {
+        let mut seed = 1u32;
+        let noise: Vec<f32> = (0..200_000).map(|_| {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (seed >> 8) as f32 / (1 << 23) as f32 - 1.0
+        }).collect();
+        // uniform: flat across the rows the noise covers
+        let bars = density_bars(&noise);
+        assert!(bars.iter().all(|&b| b > 90), "{:?}", bars);
+
+        let sine: Vec<f32> = (0..200_000).map(|i| 0.9 * (0.01 * i as f32).sin()).collect();
+        // arcsine: peaks at the extremes, lowest in the middle
+        let bars = density_bars(&sine);
+        assert!(bars[3] == 100 && bars[60] == 100, "{:?}", bars);
+        assert!(bars[5] > bars[10] && bars[10] > bars[32] && bars[32] < 20);
+        assert!(bars[58] > bars[53] && bars[53] > bars[32]);
+        assert!(bars[..3].iter().chain(&bars[61..]).all(|&b| b == 0));
+    }
+
+    #[test]
+    fn channel_scale() {
+        // Row of the brightest pixel in the middle column, for a constant input.
+        let row = |gain: f32, offset: f32| {
+            let mut scope = Scope::new(64, 64);
+            scope.set_channel_scale(0, gain, offset);
+            let mut samples = vec![-1.0];
+            samples.extend(vec![0.25; 600]);
+            scope.provide_samples(&samples);
+            (0..64).max_by(|&a, &b| scope.glow[a * 64 + 32]
+                .partial_cmp(&scope.glow[b * 64 + 32]).unwrap()).unwrap()
+        };
+        assert_eq!(row(1.0, 0.0), 24);
+        assert_eq!(row(1.0, 0.25), 8);
+        assert_eq!(row(1.0, -0.25), 40);
+        assert_eq!(row(2.0, 0.0), 16);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn idx_out_of_range() {
+        let scope = Scope::new(64, 32);
+        // in range of the buffer as a whole, but not of the row
+        scope.idx(64, 0);
+    }
+
+    #[test]
+    fn vertical_offset() {
+        let mut scope = Scope::new(64, 64);
+        scope.set_vertical_offset(-0.25);
+        let mut samples = vec![-1.0, 1.0];
+        samples.extend(vec![0.0; 600]);
+        scope.provide_samples(&samples);
+        let row = (0..64).max_by(|&a, &b| scope.glow[a * 64 + 32]
+            .partial_cmp(&scope.glow[b * 64 + 32]).unwrap()).unwrap();
+        assert_eq!(row, 48);
+    }
+
+    #[test]
+    fn vignette() {
+        // away from the grid lines, which darken pixels
+        let (w, h) = (64, 64);
+        let mut scope = Scope::new(w, h);
+        scope.glow = vec![1.0; w * h];
+        let plain = scope.as_rgba();
+        scope.set_vignette(0.8);
+        let im = scope.as_rgba();
+        let center = 4 * (31 * w + 31);
+        let edge = 4 * (30 * w + 1);
+        let corner = 4 * (w + 1);
+        // (this pixel is half a pixel off center each way, so allow rounding)
+        for k in 0..4 {
+            assert!((im[center + k] as i32 - plain[center + k] as i32).abs() <= 1);
+        }
+        assert!(im[edge + 1] < plain[edge + 1]);
+        assert!(im[corner + 1] < im[edge + 1]);
+    }
+
+    #[test]
+    fn line_step_matches_analytic() {
+        for &r in &[1.0, 2.0] {
+            let mut step = Scope::new(64, 64);
+            let mut analytic = Scope::new(64, 64);
+            step.add_line_step(20.0, 22.0, 24.0 * r, 25.0, r, 1.0);
+            analytic.add_line_analytic(20.0, 22.0, 24.0 * r, 25.0, r, 1.0);
+            let max = analytic.glow.iter().cloned().fold(0.0, f32::max);
+            for i in 0..step.glow.len() {
+                assert!((step.glow[i] - analytic.glow[i]).abs() < 0.03 * max);
+            }
+        }
+    }
+
+    // Brightness of a horizontal trace at 0.2 of the width, and total glow.
+    fn trace_brightness(sweep: f32, auto: bool) -> (f32, f32) {
+        let mut scope = Scope::new(640, 480);
+        scope.sweep = sweep;
+        scope.set_auto_intensity(auto);
+        scope.provide_samples(&[0.5; 200]);
+        let total = scope.glow.iter().sum();
+        (scope.glow[120 * 640 + 128], total)
+    }
+
+    #[test]
+    fn auto_intensity() {
+        let (slow, slow_total) = trace_brightness(0.002, false);
+        let (fast, fast_total) = trace_brightness(0.004, false);
+        assert!((slow / fast - 2.0).abs() < 0.1);
+        assert!((slow_total / fast_total - 1.0).abs() < 0.05);
+        let (slow, slow_total) = trace_brightness(0.002, true);
+        let (fast, fast_total) = trace_brightness(0.004, true);
+        assert!((slow / fast - 1.0).abs() < 0.05);
+        assert!((fast_total / slow_total - 2.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn sweep_completed() {
+        let mut scope = Scope::new(64, 64);
+        // one sweep is 500 samples after the trigger
+        let samples: Vec<f32> = (0..1000).map(|i| if i == 0 { -1.0 } else { 0.5 }).collect();
+        scope.provide_samples(&samples[..400]);
+        assert!(!scope.take_sweep_completed());
+        scope.provide_samples(&samples[400..]);
+        assert!(scope.take_sweep_completed());
+        assert!(!scope.take_sweep_completed());
+    }
+
+    #[test]
+    fn envelope_fill() {
+        let (w, h) = (64, 64);
+        let mut scope = Scope::new(w, h);
+        scope.set_envelope_fill(true);
+        // A fast carrier (much faster than the 8 or so samples per column),
+        // with its amplitude ramping up over the sweep of 500 samples.
+        let samples: Vec<f32> = (0..520).map(|i| {
+            let env = 0.1 + 0.8 * (i as f32 / 500.0).min(1.0);
+            env * (1.7 * i as f32 + 0.5).sin()
+        }).collect();
+        scope.provide_samples(&samples);
+        let filled = |i: usize| (0..h).filter(|&j| scope.glow[j * w + i] > 0.0).count();
+        for &i in &[4, 16, 32, 48, 60] {
+            let env = 0.1 + 0.8 * i as f32 / w as f32;
+            let expected = 2.0 * env * 0.5 * h as f32;
+            assert!((filled(i) as f32 - expected).abs() <= 3.0,
+                "column {}: {} vs {}", i, filled(i), expected);
+        }
+    }
+
+    #[test]
+    fn intensity_lut() {
+        // Green channel of a pixel away from the grid lines, for a given glow.
+        let ix = 10 * 64 + 10;
+        let green = |scope: &mut Scope, x: f32| {
+            scope.glow[ix] = x;
+            scope.as_rgba()[4 * ix + 1]
+        };
+        let mut scope = Scope::new(64, 64);
+        scope.set_intensity_lut(vec![0.0, 1.0]);
+        for &x in &[0.0, 0.25, 0.5, 1.0] {
+            assert_eq!(green(&mut scope, x), (x * 255.0) as u8);
+        }
+        assert_eq!(green(&mut scope, 3.0), 255);
+        scope.set_intensity_lut(vec![0.0, 1.0, 0.2]);
+        assert_eq!(green(&mut scope, 0.5), 255);
+        assert_eq!(green(&mut scope, 1.0), 51);
+        assert_eq!(green(&mut scope, 0.75), 153);
+        // an empty table restores the default
+        scope.set_intensity_lut(Vec::new());
+        let default = Scope::new(64, 64).as_rgba()[4 * ix + 1];
+        assert_eq!(green(&mut scope, 0.0), default);
+    }
+
+    #[test]
+    fn settings_clamped() {
+        let mut scope = Scope::new(64, 64);
+        scope.set_gain(2.0);
+        scope.set_sweep(0.01);
+        scope.set_persistence(500.0);
+        assert_eq!((scope.gain(), scope.sweep(), scope.persistence()), (2.0, 0.01, 500.0));
+        scope.set_gain(-1.0);
+        scope.set_sweep(0.0);
+        scope.set_persistence(0.0);
+        assert!(scope.gain() > 0.0);
+        assert!(scope.sweep() > 0.0);
+        assert_eq!(scope.persistence(), 1.0);
+        scope.set_sweep(5.0);
+        assert_eq!(scope.sweep(), 1.0);
+        // fading still works with the clamped values
+        scope.provide_samples(&[0.5; 64]);
+        assert!(scope.glow.iter().all(|x| x.is_finite()));
+    }
+
+    #[test]
+    fn trigger_modes() {
+        let mut scope = Scope::new(640, 480);
+        scope.set_trigger(Some(Trigger { level: 0.25, slope: Slope::Falling,
+            holdoff_samples: 1000 }));
+        scope.provide_samples(&[0.5; 10]);
+        assert!(!scope.is_triggered());
+        scope.provide_samples(&[0.0]);
+        assert_eq!(scope.last_trigger_sample(), Some(10));
+        // a square wave falls every 100 samples, but the holdoff skips the
+        // edges until sample 1010
+        let square: Vec<f32> = (11..1100)
+            .map(|k| if (k / 50) % 2 == 0 { 0.5 } else { 0.0 })
+            .collect();
+        scope.provide_samples(&square);
+        assert_eq!(scope.last_trigger_sample(), Some(1050));
+
+        // with no crossing, the sweep starts after a sweep's worth of waiting
+        let mut scope = Scope::new(640, 480);
+        scope.provide_samples(&[0.0; 499]);
+        assert!(!scope.is_triggered());
+        scope.provide_samples(&[0.0; 2]);
+        assert!(scope.is_triggered());
+        assert_eq!(scope.last_trigger_sample(), None);
+
+        // free-running
+        let mut scope = Scope::new(640, 480);
+        scope.set_trigger(None);
+        scope.provide_samples(&[0.0; 1]);
+        assert!(scope.is_triggered());
+        scope.provide_samples(&[0.0; 600]);
+        assert!(scope.is_triggered());
+        assert!((scope.horiz() - 0.2).abs() < 0.01);
+        assert_eq!(scope.last_trigger_sample(), None);
+    }
+
+    #[test]
+    fn xy_circle() {
+        let mut scope = Scope::new(128, 64);
+        scope.set_gain(0.5);
+        let n = 200;
+        let phase = |k: usize| 2.0 * ::std::f32::consts::PI * k as f32 / 100.0;
+        let xs: Vec<f32> = (0..n).map(|k| phase(k).cos()).collect();
+        let ys: Vec<f32> = (0..n + 50).map(|k| phase(k).sin()).collect();
+        scope.provide_samples_xy(&xs, &ys);
+        // radius 16 pixels around (64, 32)
+        let g = |i: usize, j: usize| scope.glow[j * 128 + i];
+        for &(i, j) in &[(80, 32), (48, 32), (64, 16), (64, 48)] {
+            assert!(g(i, j) > 0.1, "({}, {}): {}", i, j, g(i, j));
+        }
+        assert!(g(64, 32) < 1e-3);
+        assert!(g(100, 32) < 1e-3);
+    }
+
+    // The analytic line, drawn over the whole bounding rect.
+    fn add_line_full_rect(scope: &mut Scope, x0: f32, y0: f32, x1: f32, y1: f32, r: f32) {
+        let dx = x1 - x0;
+        let dy = y1 - y0;
+        let len = (dx * dx + dy * dy).sqrt();
+        let uscale = 2.0 / ::std::f32::consts::PI.sqrt();
+        let (vx, vy) = (-dy / (r * len), dx / (r * len));
+        let (ux, uy) = (vy * uscale, -vx * uscale);
+        let (u0, v0) = (-x0 * ux - y0 * uy, -x0 * vx - y0 * vy);
+        let ustep = dx * ux + dy * uy;
+        let amp = r / uscale / len;
+        let c = scope.clip_factor * r;
+        let i0 = ((x0.min(x1) - c).ceil().max(0.0) as usize).min(scope.width);
+        let i1 = ((x0.max(x1) + c).ceil().max(0.0) as usize).min(scope.width);
+        let j0 = ((y0.min(y1) - c).ceil().max(0.0) as usize).min(scope.height);
+        let j1 = ((y0.max(y1) + c).ceil().max(0.0) as usize).min(scope.height);
+        for j in j0..j1 {
+            for i in i0..i1 {
+                let u = ux * (i as f32) + uy * (j as f32) + u0;
+                let v = vx * (i as f32) + vy * (j as f32) + v0;
+                scope.glow[j * scope.width + i] +=
+                    amp * gauss_approx(v) * (erf_approx(u) - erf_approx(u - ustep));
+            }
+        }
+    }
+
+    #[test]
+    fn scanline_bounds_match_full_rect() {
+        let lines = [(10.0, 10.0, 90.0, 70.0, 1.5), (5.0, 60.0, 95.0, 58.0, 1.0),
+            (50.0, 5.0, 52.0, 75.0, 2.0), (90.0, 10.0, 20.0, 75.0, 1.0),
+            (-10.0, 40.0, 110.0, 41.0, 1.0)];
+        for &(x0, y0, x1, y1, r) in &lines {
+            let mut fast = Scope::new(100, 80);
+            let mut full = Scope::new(100, 80);
+            fast.add_line_analytic(x0, y0, x1, y1, r, 1.0);
+            add_line_full_rect(&mut full, x0, y0, x1, y1, r);
+            // Only pixels beyond the clip distance from the line are skipped,
+            // and those are in the far tail of the gaussian.
+            let max = full.glow.iter().cloned().fold(0.0, f32::max);
+            for (a, b) in fast.glow.iter().zip(&full.glow) {
+                assert!((a - b).abs() <= 5e-3 * max, "{} vs {}", a, b);
+            }
+            let sum_fast: f32 = fast.glow.iter().sum();
+            let sum_full: f32 = full.glow.iter().sum();
+            assert!((sum_fast - sum_full).abs() < 0.01 * sum_full);
+        }
+    }
+
+    #[test]
+    fn color_maps() {
+        let mut scope = Scope::new(64, 64);
+        let lit = 5 * 64 + 5;
+        scope.glow[lit] = 1.0;
+        let px = |im: &[u8], i: usize| [im[i * 4], im[i * 4 + 1], im[i * 4 + 2], im[i * 4 + 3]];
+        let im = scope.as_rgba_with(&ColorMap::green());
+        assert_eq!(px(&im, lit)[3], 255);
+        assert_eq!(px(&im, 0)[3], 255);
+        assert!(px(&im, 0)[1] > 0);
+        let im = scope.as_rgba_with(&ColorMap::amber().transparent());
+        assert_eq!(px(&im, 0), [0, 0, 0, 0]);
+        let [r, g, b, a] = px(&im, lit);
+        assert!(r > g && g > b, "{:?}", (r, g, b));
+        assert_eq!(a, r);
+        let map = ColorMap { background: [10, 20, 30, 128], ..ColorMap::white().transparent() };
+        let im = scope.as_rgba_with(&map);
+        assert_eq!(px(&im, 0), [10, 20, 30, 128]);
+        assert_eq!(px(&im, lit), [245, 255, 255, 255]);
+    }
+
+    #[test]
+    fn persistence_secs() {
+        let mut scope = Scope::new(64, 64);
+        scope.set_persistence_secs(0.05);
+        assert!((scope.persistence() - 2205.0).abs() < 1e-2);
+        scope.set_sample_rate(48_000.0);
+        assert!((scope.persistence() - 2400.0).abs() < 1e-2);
+        scope.set_persistence(100.0);
+        scope.set_sample_rate(96_000.0);
+        assert_eq!(scope.persistence(), 100.0);
+    }
+
+    #[test]
+    fn decay_independent_of_block_size() {
+        // a tone starting partway in, so that some samples wait for the
+        // trigger, and several sweeps
+        let samples: Vec<f32> = (0..4096)
+            .map(|k| if k < 300 { 0.0 } else { 0.7 * (k as f32 * 0.05).sin() })
+            .collect();
+        let render = |block: usize| {
+            let mut scope = Scope::new(128, 96);
+            scope.set_persistence(800.0);
+            for chunk in samples.chunks(block) {
+                scope.provide_samples(chunk);
+            }
+            scope.glow
+        };
+        let reference = render(4096);
+        let max = reference.iter().cloned().fold(0.0, f32::max);
+        for &block in &[64, 512, 100] {
+            for (a, b) in render(block).iter().zip(&reference) {
+                assert!((a - b).abs() < 1e-3 * max, "block {}: {} vs {}", block, a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn resize_keeps_settings() {
+        let mut scope = Scope::new(64, 48);
+        scope.set_gain(0.5);
+        scope.set_sweep(0.01);
+        scope.glow[10 * 64 + 20] = 1.0;
+        scope.provide_samples(&[-0.1, 0.1, 0.2]);
+        let horiz = scope.horiz();
+        scope.resize(128, 96);
+        assert_eq!((scope.gain(), scope.sweep(), scope.horiz()), (0.5, 0.01, horiz));
+        assert_eq!(scope.glow.len(), 128 * 96);
+        assert_eq!(scope.density.len(), 96);
+        assert!(scope.xylast.is_none());
+        // the dot is scaled up with the image
+        assert!(scope.glow[21 * 128 + 41] > 0.5);
+        assert!(scope.glow[10 * 128 + 20] < 1e-3);
+        // drawing continues at the new size
+        scope.provide_samples(&[0.3; 50]);
+        assert_eq!(scope.as_rgba().len(), 128 * 96 * 4);
+    }
+
+    #[test]
+    #[should_panic]
+    fn resize_zero() {
+        Scope::new(64, 48).resize(0, 48);
+    }
+
+    #[test]
+    fn add_dot_simd_matches_scalar() {
+        let dots = [(20.3, 15.7, 1.0), (3.1, 40.0, 2.5), (50.0, 30.5, 7.3), (62.5, 0.5, 4.0),
+            (31.0, 24.0, 20.0)];
+        for &mode in &[BlendMode::Add, BlendMode::Max] {
+            // A background, so that the blend modes give different results.
+            let mut scalar = Scope::new(64, 48);
+            scalar.set_blend_mode(mode);
+            for g in scalar.glow.iter_mut() {
+                *g = 0.2;
+            }
+            let mut expected = scalar.glow.clone();
+            for &(x, y, r) in &dots {
+                scalar.add_dot_scalar(x, y, r, 0.7);
+                let (i0, i1, j0, j1) = scalar.dot_bounds(x, y, r);
+                for j in j0..j1 {
+                    for i in i0..i1 {
+                        let z = gauss_approx((i as f32 - x) / r)
+                            * gauss_approx((j as f32 - y) / r) * 0.7;
+                        let g = &mut expected[j * 64 + i];
+                        *g = match mode {
+                            BlendMode::Add => *g + z,
+                            BlendMode::Max => g.max(z),
+                        };
+                    }
+                }
+            }
+            for (a, b) in scalar.glow.iter().zip(&expected) {
+                assert!((a - b).abs() <= 1e-5 * b.max(1.0), "{:?}: {} vs {}", mode, a, b);
+            }
+
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            {
+                if is_x86_feature_detected!("avx") {
+                    let mut fast = Scope::new(64, 48);
+                    fast.set_blend_mode(mode);
+                    for g in fast.glow.iter_mut() {
+                        *g = 0.2;
+                    }
+                    for &(x, y, r) in &dots {
+                        unsafe { fast.add_dot_avx(x, y, r, 0.7); }
+                    }
+                    for (a, b) in fast.glow.iter().zip(&scalar.glow) {
+                        assert!((a - b).abs() <= 1e-5 * b.max(1.0), "{:?}: {} vs {}", mode, a, b);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn custom_render() {
+        let mut scope = Scope::new(64, 48);
+        scope.add_dot(10.0, 20.0, 1.0, 1.0);
+        assert_eq!(scope.dimensions(), (64, 48));
+        let (w, h) = scope.dimensions();
+        assert_eq!(scope.glow().len(), w * h);
+        assert!(scope.glow()[20 * w + 10] > 0.9);
+        // the grid alone, on a white image, matches as_rgba with no glow
+        let mut im = vec![255; w * h * 4];
+        scope.render_grid_lines(&mut im);
+        let blank = Scope::new(64, 48).as_rgba();
+        for (a, b) in im.chunks(4).zip(blank.chunks(4)) {
+            assert_eq!(a[0] == 255, b[0] == 2);
+        }
+    }
+
+    #[test]
+    fn two_channels() {
+        let mut scope = Scope::new_multi(128, 96, 2);
+        let a = vec![0.5; 256];
+        let b = vec![-0.5; 256];
+        scope.provide_samples(&[-0.1]);
+        for _ in 0..4 {
+            scope.provide_samples_channel(0, &a);
+            scope.provide_samples_channel(1, &b);
+        }
+        // channel 0 at row 24, channel 1 at row 72, in different colors
+        // (measured just off the traces, where the colors aren't saturated,
+        // relative to the unlit screen)
+        let im = scope.as_rgba();
+        let blank = Scope::new(128, 96).as_rgba();
+        let lit = |i: usize, j: usize| {
+            let ix = (j * 128 + i) * 4;
+            [im[ix] - blank[ix], im[ix + 1] - blank[ix + 1]]
+        };
+        let (p0, p1) = (lit(40, 26), lit(40, 74));
+        assert!(p0[1] > 2 * p0[0], "{:?}", p0);
+        assert!(p1[0] > p1[1] + 20, "{:?}", p1);
+        assert!(scope.glow[72 * 128 + 40] < 1e-3);
+        assert!(scope.channels[0].glow[72 * 128 + 40] > 0.1);
+        assert!(scope.channels[0].glow[24 * 128 + 40] < 1e-3);
+        // channels fade independently
+        let before = scope.channels[0].glow.clone();
+        scope.provide_samples(&a);
+        assert_eq!(scope.channels[0].glow, before);
+        scope.provide_samples_channel(1, &[]);
+        assert_eq!(scope.channels[0].glow, before);
+        // with no block on channel 0, there's nothing to draw, only fading
+        scope.provide_samples(&[]);
+        scope.provide_samples_channel(1, &b[..100]);
+        let expected = (-100.0 / 1500.0f32).exp();
+        for (a, b) in scope.channels[0].glow.iter().zip(&before) {
+            assert!((a - b * expected).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn two_channels_glow_ops() {
+        let mut scope = Scope::new_multi(16, 16, 2);
+        scope.add_dot(4.0, 4.0, 1.0, 1.0);
+        scope.channels[0].glow[8 * 16 + 8] = 2.0;
+        scope.fade(0.5);
+        assert!((scope.glow[4 * 16 + 4] - 0.5).abs() < 0.01);
+        assert_eq!(scope.channels[0].glow[8 * 16 + 8], 1.0);
+        // the brightest pixel overall is on channel 1
+        let hist = scope.glow_histogram(4);
+        assert_eq!(hist.iter().sum::<u32>(), 2 * 16 * 16);
+        assert_eq!(hist[3], 1);
+        scope.apply_kernel(&[1.0 / 9.0; 9], 3, 3);
+        assert!((scope.channels[0].glow[8 * 16 + 9] - 1.0 / 9.0).abs() < 1e-6);
+        assert!(scope.glow[4 * 16 + 5] > 0.0);
+        scope.set_channel_scale(1, 0.5, 0.25);
+        assert_eq!(scope.channel_scales[1], (0.5, 0.25));
+    }
+
+    #[test]
+    #[should_panic]
+    fn channel_scale_out_of_range() {
+        Scope::new(16, 16).set_channel_scale(1, 1.0, 0.0);
+    }
+
+    #[test]
+    fn auto_gain() {
+        let mut scope = Scope::new(64, 64);
+        scope.set_auto_gain(true);
+        // silence is boosted only as far as the limit
+        scope.provide_samples(&[0.0; 256]);
+        assert_eq!(scope.effective_gain(), 100.0);
+        let tone = |amp: f32| -> Vec<f32> {
+            (0..4410).map(|k| amp * (k as f32 * 0.1).sin()).collect()
+        };
+        scope.provide_samples(&tone(0.01));
+        assert!((scope.effective_gain() - 80.0).abs() < 1.0, "{}", scope.effective_gain());
+        // a loud block is scaled down at once
+        scope.provide_samples(&tone(2.0));
+        assert!((scope.effective_gain() - 0.4).abs() < 0.01);
+        // and a quiet one comes back up slowly
+        scope.provide_samples(&tone(0.01));
+        assert!(scope.effective_gain() < 0.5);
+        for _ in 0..100 {
+            scope.provide_samples(&tone(0.01));
+        }
+        assert!((scope.effective_gain() - 80.0).abs() < 1.0);
+        // the gain setting multiplies the auto gain
+        scope.set_gain(0.5);
+        scope.provide_samples(&tone(0.01));
+        assert!((scope.effective_gain() - 40.0).abs() < 0.5);
+        scope.set_auto_gain(false);
+        assert_eq!(scope.effective_gain(), 0.5);
+    }
+
+    #[test]
+    fn auto_gain_fills_height() {
+        // A quiet sine at the default gain fills about 80% of the height.
+        let mut scope = Scope::new(64, 64);
+        scope.set_auto_gain(true);
+        let x: Vec<f32> = (0..4410).map(|k| 0.05 * (k as f32 * 0.1).sin()).collect();
+        scope.provide_samples(&x);
+        assert!((scope.effective_gain() - 16.0).abs() < 0.2, "{}", scope.effective_gain());
+        let row_max = |j: usize| scope.glow[j * 64..(j + 1) * 64].iter().cloned().fold(0.0, f32::max);
+        // the peaks are at rows 6.4 and 57.6
+        assert!(row_max(6) > 0.1 && row_max(57) > 0.1, "{} {}", row_max(6), row_max(57));
+        assert!(row_max(2) < 1e-3 && row_max(61) < 1e-3);
+    }
+
+    #[test]
+    fn auto_gain_other_displays() {
+        let xs: Vec<f32> = (0..512).map(|k| (k as f32 * 0.05).cos()).collect();
+        let ys: Vec<f32> = (0..512).map(|k| 0.5 * (k as f32 * 0.05).sin()).collect();
+        let mut xy = Scope::new(64, 64);
+        xy.set_auto_gain(true);
+        xy.provide_samples_xy(&xs, &ys);
+        assert!((xy.effective_gain() - 0.8).abs() < 0.01, "{}", xy.effective_gain());
+        // the trace reaches 80% of the way to the left and right edges
+        assert!(xy.glow[32 * 64 + 6] > 0.01 && xy.glow[32 * 64 + 57] > 0.01);
+        assert!(xy.glow[32 * 64 + 2] < 1e-3);
+
+        let mut eye = Scope::new(64, 64);
+        eye.set_auto_gain(true);
+        eye.provide_eye(&xs, 64);
+        assert!((eye.effective_gain() - 0.8).abs() < 0.01);
+
+        // A quiet signal is spread over most of the height, not bunched
+        // around the center.
+        let quiet: Vec<f32> = ys.iter().map(|y| 0.1 * y).collect();
+        let mut density = Scope::new(64, 64);
+        density.set_auto_gain(true);
+        density.provide_density(&quiet);
+        assert!((density.effective_gain() - 16.0).abs() < 0.2);
+        let rows: Vec<usize> = (0..64).filter(|&j| density.density[j] > 0.0).collect();
+        assert!(rows[0] <= 7 && rows[rows.len() - 1] >= 56, "{:?}", rows);
+    }
+
+    #[test]
+    fn state_getters() {
+        let mut scope = Scope::new(640, 480);
+        assert!(!scope.is_triggered());
+        assert_eq!(scope.last_trigger_sample(), None);
+        scope.provide_samples(&[-0.5; 10]);
+        assert!(!scope.is_triggered());
+        scope.provide_samples(&[0.5; 101]);
+        assert!(scope.is_triggered());
+        assert_eq!(scope.last_trigger_sample(), Some(10));
+        assert!((scope.horiz() - 0.2).abs() < 1e-5);
+        assert!((scope.current_sweep_progress() - 0.2).abs() < 1e-5);
+        // finish the sweep
+        scope.provide_samples(&[0.5; 450]);
+        assert!(!scope.is_triggered());
+        assert_eq!(scope.current_sweep_progress(), 1.0);
+    }
+
+    #[test]
+    fn histogram() {
+        let mut scope = Scope::new(4, 4);
+        assert_eq!(scope.glow_histogram(4), vec![16, 0, 0, 0]);
+        for (i, x) in [0.5, 1.0, 1.5, 2.0, 2.0, 3.9, 4.0].iter().enumerate() {
+            scope.glow[i] = *x;
+        }
+        assert_eq!(scope.glow_histogram(4), vec![10, 2, 2, 2]);
+        assert_eq!(scope.glow_histogram(1), vec![16]);
+        assert_eq!(scope.glow_histogram(0), vec![]);
+    }
+
+    #[test]
+    fn box_kernel() {
+        let mut scope = Scope::new(8, 8);
+        scope.glow[3 * 8 + 4] = 9.0;
+        scope.glow[0] = 9.0;
+        scope.apply_kernel(&[1.0 / 9.0; 9], 3, 3);
+        for j in 0..8 {
+            for i in 0..8 {
+                let expected = if (i as isize - 4).abs() <= 1 && (j as isize - 3).abs() <= 1 {
+                    1.0
+                } else if i <= 1 && j <= 1 {
+                    // the corner dot is counted repeatedly by clamping
+                    if i == 0 && j == 0 { 4.0 } else if i == 0 || j == 0 { 2.0 } else { 1.0 }
+                } else {
+                    0.0
+                };
+                assert!((scope.glow[j * 8 + i] - expected).abs() < 1e-5, "({}, {})", i, j);
+            }
+        }
+    }
+
+    #[test]
+    fn eye_is_open() {
+        let mut scope = Scope::new(640, 480);
+        let sps = 64;
+        let samples: Vec<f32> = (0..sps * 40)
+            .map(|k| 0.8 * (::std::f32::consts::PI * k as f32 / sps as f32).sin())
+            .collect();
+        scope.provide_eye(&samples, sps);
+        let max = scope.glow.iter().cloned().fold(0.0, f32::max);
+        assert!(max > 0.0);
+        for j in 200..280 {
+            for i in 260..380 {
+                assert!(scope.glow[j * 640 + i] < 0.03 * max);
+            }
+        }
+    }
 }
@@ -0,0 +1,206 @@
+// Copyright 2018 The Synthesizer IO Authors.
+// 
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+// 
+//     https://www.apache.org/licenses/LICENSE-2.0
+// 
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Serialization of the scope's glow and settings, for saving a capture or
+//! sending it elsewhere.
+//!
+//! The format is a 4 byte magic number, the width, height and number of
+//! channels as little-endian u32, the time constant, sweep and gain as
+//! little-endian f32, then the glow of each channel in turn, row by row, as
+//! little-endian f32.
+
+use std::error::Error;
+use std::fmt;
+
+use Scope;
+
+const MAGIC: &[u8; 4] = b"SCP2";
+const HEADER_LEN: usize = 4 + 3 * 4 + 3 * 4;
+
+/// An error loading a saved scope state.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StateError {
+    /// The data doesn't start with the expected magic number.
+    BadMagic,
+    /// The data is shorter than its header says.
+    Truncated,
+    /// The saved dimensions (width, height) don't match this scope.
+    SizeMismatch { expected: (usize, usize), found: (usize, usize) },
+    /// The saved number of channels doesn't match this scope.
+    ChannelMismatch { expected: usize, found: usize },
+    /// A saved setting (time constant, sweep or gain) isn't a positive,
+    /// finite number.
+    BadSetting,
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StateError::BadMagic => write!(f, "not a saved scope state"),
+            StateError::Truncated => write!(f, "saved scope state is truncated"),
+            StateError::SizeMismatch { expected, found } =>
+                write!(f, "saved scope state is {}x{}, but the scope is {}x{}",
+                    found.0, found.1, expected.0, expected.1),
+            StateError::ChannelMismatch { expected, found } =>
+                write!(f, "saved scope state has {} channels, but the scope has {}",
+                    found, expected),
+            StateError::BadSetting => write!(f, "saved scope state has an invalid setting"),
+        }
+    }
+}
+
+impl Error for StateError {
+    fn description(&self) -> &str {
+        "error loading scope state"
+    }
+}
+
+fn push_u32(buf: &mut Vec<u8>, x: u32) {
+    for i in 0..4 {
+        buf.push((x >> (8 * i)) as u8);
+    }
+}
+
+fn read_u32(data: &[u8]) -> u32 {
+    (0..4).fold(0, |acc, i| acc | (data[i] as u32) << (8 * i))
+}
+
+impl Scope {
+    /// Serialize the glow of every channel, dimensions, and settings (time
+    /// constant, sweep and gain).
+    pub fn save_state(&self) -> Vec<u8> {
+        let n_channels = self.channels.len() + 1;
+        let mut buf = Vec::with_capacity(HEADER_LEN + 4 * n_channels * self.glow.len());
+        buf.extend_from_slice(MAGIC);
+        push_u32(&mut buf, self.width as u32);
+        push_u32(&mut buf, self.height as u32);
+        push_u32(&mut buf, n_channels as u32);
+        let glows = self.glow.iter().chain(self.channels.iter().flat_map(|c| &c.glow));
+        for &x in [self.tc, self.sweep, self.gain].iter().chain(glows) {
+            push_u32(&mut buf, x.to_bits());
+        }
+        buf
+    }
+
+    /// Restore the state saved by `save_state`. The scope must have the same
+    /// dimensions and number of channels as the one that was saved; on
+    /// error, the scope is unchanged.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), StateError> {
+        if data.len() < 4 || &data[..4] != MAGIC {
+            return Err(StateError::BadMagic);
+        }
+        if data.len() < HEADER_LEN {
+            return Err(StateError::Truncated);
+        }
+        let found = (read_u32(&data[4..]) as usize, read_u32(&data[8..]) as usize);
+        let expected = (self.width, self.height);
+        if found != expected {
+            return Err(StateError::SizeMismatch { expected, found });
+        }
+        let n_channels = self.channels.len() + 1;
+        let found = read_u32(&data[12..]) as usize;
+        if found != n_channels {
+            return Err(StateError::ChannelMismatch { expected: n_channels, found });
+        }
+        let n = self.glow.len();
+        if data.len() < HEADER_LEN + 4 * n_channels * n {
+            return Err(StateError::Truncated);
+        }
+        let f = |i: usize| f32::from_bits(read_u32(&data[16 + 4 * i..]));
+        let (tc, sweep, gain) = (f(0), f(1), f(2));
+        if [tc, sweep, gain].iter().any(|x| !(x.is_finite() && *x > 0.0)) {
+            return Err(StateError::BadSetting);
+        }
+        self.tc = tc;
+        self.sweep = sweep;
+        self.gain = gain;
+        self.effective_gain = gain;
+        if self.persistence_secs.is_some() {
+            self.persistence_secs = Some(tc / self.sample_rate);
+        }
+        for i in 0..n {
+            self.glow[i] = f(3 + i);
+        }
+        for (c, channel) in self.channels.iter_mut().enumerate() {
+            for i in 0..n {
+                channel.glow[i] = f(3 + (c + 1) * n + i);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use Scope;
+    use super::StateError;
+
+    #[test]
+    fn round_trip() {
+        let mut scope = Scope::new(64, 32);
+        scope.add_line(3.0, 5.0, 60.0, 25.0, 1.5, 1.0);
+        scope.add_dot(10.0, 10.0, 2.0, 0.3);
+        let data = scope.save_state();
+        let mut restored = Scope::new(64, 32);
+        assert_eq!(restored.load_state(&data), Ok(()));
+        assert_eq!(restored.glow, scope.glow);
+        assert_eq!((restored.tc, restored.sweep, restored.gain),
+            (scope.tc, scope.sweep, scope.gain));
+
+        let mut other = Scope::new(32, 64);
+        assert_eq!(other.load_state(&data),
+            Err(StateError::SizeMismatch { expected: (32, 64), found: (64, 32) }));
+        assert_eq!(restored.load_state(&data[..100]), Err(StateError::Truncated));
+        assert_eq!(restored.load_state(b"nope"), Err(StateError::BadMagic));
+    }
+
+    #[test]
+    fn channels() {
+        let mut scope = Scope::new_multi(32, 16, 2);
+        scope.add_dot(5.0, 5.0, 2.0, 1.0);
+        scope.channels[0].glow[100] = 0.7;
+        let data = scope.save_state();
+        let mut restored = Scope::new_multi(32, 16, 2);
+        assert_eq!(restored.load_state(&data), Ok(()));
+        assert_eq!(restored.glow, scope.glow);
+        assert_eq!(restored.channels[0].glow, scope.channels[0].glow);
+        assert_eq!(restored.channels[0].glow[100], 0.7);
+
+        assert_eq!(Scope::new(32, 16).load_state(&data),
+            Err(StateError::ChannelMismatch { expected: 1, found: 2 }));
+        assert_eq!(restored.load_state(&data[..data.len() - 4]), Err(StateError::Truncated));
+    }
+
+    #[test]
+    fn bad_settings() {
+        let data = Scope::new(16, 16).save_state();
+        let mut scope = Scope::new(16, 16);
+        scope.set_persistence_secs(1.0);
+        for &(offset, x) in &[(16, 0.0), (20, -1.0), (24, ::std::f32::NAN),
+            (16, ::std::f32::INFINITY)]
+        {
+            let mut bad = data.clone();
+            let bits = (x as f32).to_bits();
+            for i in 0..4 {
+                bad[offset + i] = (bits >> (8 * i)) as u8;
+            }
+            assert_eq!(scope.load_state(&bad), Err(StateError::BadSetting));
+        }
+        assert_eq!(scope.persistence(), 44_100.0);
+        // the restored time constant, in seconds, follows the sample rate
+        assert_eq!(scope.load_state(&data), Ok(()));
+        scope.set_sample_rate(48_000.0);
+        assert!((scope.persistence() - 1_500.0 * 48_000.0 / 44_100.0).abs() < 0.1);
+    }
+}
@@ -28,6 +28,9 @@ pub struct Spect {
     ibuf: Vec<Complex<f32>>,
     obuf: Vec<Complex<f32>>,
     fft: Arc<FFT<f32>>,
+    sample_rate: f32,
+    freq_cursor: Option<f32>,
+    have_frame: bool,
 }
 
 impl Spect {
@@ -37,7 +40,62 @@ impl Spect {
         let window = Self::mk_window(width);
         let ibuf = vec![Default::default(); width];
         let obuf = vec![Default::default(); width];
-        Spect { window, ibuf, obuf, fft }
+        let sample_rate = 44_100.0;
+        let freq_cursor = None;
+        let have_frame = false;
+        Spect { window, ibuf, obuf, fft, sample_rate, freq_cursor, have_frame }
+    }
+
+    /// Set the sample rate of the input, used to map frequencies to bins.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+    }
+
+    /// Set (or clear) a frequency cursor, in Hz. When set, `generate` draws
+    /// a marker line at the row corresponding to that frequency.
+    pub fn set_freq_cursor(&mut self, hz: Option<f32>) {
+        self.freq_cursor = hz;
+    }
+
+    /// The image row of the frequency cursor, if it is set and in range.
+    ///
+    /// The frequency axis is linear, with DC at the bottom row.
+    pub fn freq_cursor_row(&self) -> Option<usize> {
+        let bin = self.freq_cursor_bin()?.round() as usize;
+        let height = self.window.len() / 2;
+        if bin < height {
+            Some(height - 1 - bin)
+        } else {
+            None
+        }
+    }
+
+    /// The magnitude at the cursor frequency in the last FFT frame, in dB.
+    ///
+    /// The magnitude is interpolated linearly between adjacent bins.
+    pub fn freq_cursor_level(&self) -> Option<f32> {
+        if !self.have_frame {
+            return None;
+        }
+        let bin = self.freq_cursor_bin()?;
+        let height = self.window.len() / 2;
+        if bin > (height - 1) as f32 {
+            return None;
+        }
+        let i = bin.floor() as usize;
+        let frac = bin - i as f32;
+        let mut mag = self.obuf[i].norm();
+        if frac > 0.0 {
+            mag += frac * (self.obuf[i + 1].norm() - mag);
+        }
+        Some(20.0 * (mag + 1e-6).log10())
+    }
+
+    // The fractional FFT bin corresponding to the cursor frequency.
+    fn freq_cursor_bin(&self) -> Option<f32> {
+        let hz = self.freq_cursor?;
+        let bin = hz * self.window.len() as f32 / self.sample_rate;
+        if bin >= 0.0 { Some(bin) } else { None }
     }
 
     pub fn image_dims(&self, n_samples: usize) -> (usize, usize) {
@@ -58,15 +116,61 @@ impl Spect {
             self.fill_column(&mut img, x, width);
             ix += step;
         }
+        if let Some(row) = self.freq_cursor_row() {
+            let stride = width * 4;
+            for px in img[row * stride..(row + 1) * stride].chunks_mut(4) {
+                px[0] = 0;
+                px[1] = 255;
+                px[2] = 255;
+            }
+        }
         img
     }
 
+    /// Compute the spectrum of a single frame, for measurements such as
+    /// `thd` and `freq_cursor_level`. The input must be the size of the window.
+    pub fn analyze(&mut self, data: &[f32]) {
+        assert_eq!(data.len(), self.window.len());
+        self.compute_one_window(data);
+    }
+
+    /// The total harmonic distortion of a sine response in the last FFT frame:
+    /// the RMS of the harmonics (up to Nyquist) relative to the fundamental,
+    /// as a ratio (not a percentage). Returns 0 if there is no frame yet, or
+    /// if the fundamental isn't positive and below Nyquist. At most one
+    /// harmonic per bin is counted.
+    pub fn thd(&self, fundamental_hz: f32, sample_rate: f32) -> f32 {
+        if !(self.have_frame && fundamental_hz > 0.0 && fundamental_hz < 0.5 * sample_rate) {
+            return 0.0;
+        }
+        let width = self.window.len();
+        let height = width / 2;
+        // Energy within the main lobe of the Hann window around a frequency.
+        let energy = |hz: f32| {
+            let center = (hz * width as f32 / sample_rate).round() as usize;
+            let lo = center.saturating_sub(2);
+            let hi = (center + 3).min(height);
+            self.obuf[lo..hi].iter().map(|z| z.norm_sqr()).sum::<f32>()
+        };
+        let fundamental = energy(fundamental_hz);
+        let mut harmonics = 0.0;
+        for k in 2..height + 2 {
+            let hz = k as f32 * fundamental_hz;
+            if hz >= 0.5 * sample_rate {
+                break;
+            }
+            harmonics += energy(hz);
+        }
+        (harmonics / (fundamental + 1e-12)).sqrt()
+    }
+
     // Compute one slice worth of spectrum. On input, `data` is the same size as the window.
     fn compute_one_window(&mut self, data: &[f32]) {
         for ((i, w), o) in data.iter().zip(self.window.iter()).zip(self.ibuf.iter_mut()) {
             *o = (i * w).into();
         }
         self.fft.process(&mut self.ibuf, &mut self.obuf);
+        self.have_frame = true;
     }
 
     fn fill_column(&self, img: &mut [u8], x: usize, width: usize) {
@@ -97,3 +201,57 @@ impl Spect {
         (0..width).map(|i| 0.5 - 0.5 * (i as f32 * d).cos()).collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn freq_cursor() {
+        let mut spect = Spect::new(256);
+        spect.set_sample_rate(48_000.0);
+        assert_eq!(spect.freq_cursor_row(), None);
+        // 3000 Hz is bin 16 of 128, counted up from the bottom row.
+        spect.set_freq_cursor(Some(3000.0));
+        assert_eq!(spect.freq_cursor_row(), Some(127 - 16));
+        assert_eq!(spect.freq_cursor_level(), None);
+        spect.set_freq_cursor(Some(30_000.0));
+        assert_eq!(spect.freq_cursor_row(), None);
+
+        let input: Vec<f32> = (0..1024)
+            .map(|i| (2.0 * PI * 3000.0 * i as f32 / 48_000.0).sin())
+            .collect();
+        spect.set_freq_cursor(Some(3000.0));
+        let (width, _) = spect.image_dims(input.len());
+        let img = spect.generate(&input);
+        let ix = 4 * (width * (127 - 16) + width / 2);
+        assert_eq!(&img[ix..ix + 3], &[0, 255, 255]);
+        let on_peak = spect.freq_cursor_level().unwrap();
+        spect.set_freq_cursor(Some(6000.0));
+        let off_peak = spect.freq_cursor_level().unwrap();
+        assert!(on_peak > off_peak + 40.0, "{} {}", on_peak, off_peak);
+    }
+
+    #[test]
+    fn thd() {
+        let sr = 48_000.0;
+        let sine: Vec<f32> = (0..1024)
+            .map(|i| (2.0 * PI * 1000.0 * i as f32 / sr).sin())
+            .collect();
+        let mut spect = Spect::new(1024);
+        spect.analyze(&sine);
+        assert!(spect.thd(1000.0, sr) < 0.001, "{}", spect.thd(1000.0, sr));
+        assert_eq!(spect.thd(24_000.0, sr), 0.0);
+        assert_eq!(spect.thd(30_000.0, sr), 0.0);
+        assert_eq!(spect.thd(0.0, sr), 0.0);
+        assert_eq!(spect.thd(-1000.0, sr), 0.0);
+        assert_eq!(spect.thd(f32::NAN, sr), 0.0);
+        // a tiny fundamental finishes, counting at most one harmonic per bin
+        assert!(spect.thd(1e-6, sr).is_finite());
+        // Clipping at half the peak gives roughly 20% THD.
+        let clipped: Vec<f32> = sine.iter().map(|x| x.max(-0.5).min(0.5)).collect();
+        spect.analyze(&clipped);
+        let thd = spect.thd(1000.0, sr);
+        assert!(thd > 0.15 && thd < 0.3, "{}", thd);
+    }
+}
@@ -18,6 +18,12 @@ use std::any::Any;
 
 pub const N_SAMPLES_PER_CHUNK: usize = 32;
 
+// maximum number of buffers for the default `process_in_place`
+const MAX_IN_PLACE_BUFS: usize = 16;
+
+// maximum number of control outputs for the default `process_in_place`
+const MAX_IN_PLACE_CTRL: usize = 16;
+
 pub struct Buffer {
     // TODO: simd alignment
     buf: [f32; N_SAMPLES_PER_CHUNK],
@@ -53,6 +59,10 @@ pub trait Module: ToAny + Send {
     /// Report the number of control values this module is expected to generate.
     fn n_ctrl_out(&self) -> usize { 0 }
 
+    /// Report the number of control inputs this module reads, so the host
+    /// knows how many to connect.
+    fn n_ctrl_in(&self) -> usize { 0 }
+
     /// Give modules an opportunity to migrate state from the previous module
     /// when it is replaced.
     #[allow(unused)]
@@ -62,6 +72,37 @@ pub trait Module: ToAny + Send {
     fn process(&mut self, control_in: &[f32], control_out: &mut [f32],
         buf_in: &[&Buffer], buf_out: &mut [Buffer]);
 
+    /// Process one chunk of audio in place: `buf` holds the input buffers on
+    /// entry and the output buffers on return, so the number of inputs must
+    /// match `n_bufs_out`. Any control outputs are discarded.
+    ///
+    /// The default implementation copies the input to temporaries and calls
+    /// `process`; modules that can operate in place should override it to avoid
+    /// the copy. It is lock-free and allocation-free for up to 16 buffers and
+    /// 16 control outputs; beyond that, the temporaries are allocated, so
+    /// such modules should override it if they run on the audio thread.
+    fn process_in_place(&mut self, control_in: &[f32], buf: &mut [Buffer]) {
+        let n = buf.len();
+        let n_ctrl_out = self.n_ctrl_out();
+        if n > MAX_IN_PLACE_BUFS || n_ctrl_out > MAX_IN_PLACE_CTRL {
+            let tmp: Vec<Buffer> = buf.iter().map(|b| Buffer { buf: *b.get() }).collect();
+            let ptrs: Vec<&Buffer> = tmp.iter().collect();
+            let mut ctrl_out = vec![0.0; n_ctrl_out];
+            self.process(control_in, &mut ctrl_out, &ptrs, buf);
+            return;
+        }
+        let mut tmp: [Buffer; MAX_IN_PLACE_BUFS] = Default::default();
+        for (t, b) in tmp.iter_mut().zip(buf.iter()) {
+            t.get_mut().copy_from_slice(b.get());
+        }
+        let mut ptrs = [&tmp[0]; MAX_IN_PLACE_BUFS];
+        for i in 0..n {
+            ptrs[i] = &tmp[i];
+        }
+        let mut ctrl_out = [0.0; MAX_IN_PLACE_CTRL];
+        self.process(control_in, &mut ctrl_out[..n_ctrl_out], &ptrs[..n], buf);
+    }
+
     /// Process one chunk of audio. Implementations are expected to be lock-free.
     /// Implementations should override this method if they require a timestamp,
     /// otherwise `process`.
@@ -88,3 +129,55 @@ pub trait ToAny {
 impl<T: Sized + 'static> ToAny for T {
     fn to_any(&mut self) -> &mut dyn Any { self }
 }
+
+#[cfg(test)]
+mod tests {
+    use modules::Tilt;
+    use super::{Module, Buffer};
+
+    // Adds one to each of many buffers, and reports many control outputs.
+    struct Wide;
+
+    impl Module for Wide {
+        fn n_bufs_out(&self) -> usize { 20 }
+
+        fn n_ctrl_out(&self) -> usize { 20 }
+
+        fn process(&mut self, _control_in: &[f32], control_out: &mut [f32],
+            buf_in: &[&Buffer], buf_out: &mut [Buffer])
+        {
+            for (x, y) in buf_in.iter().zip(buf_out.iter_mut()) {
+                for (x, y) in x.get().iter().zip(y.get_mut().iter_mut()) {
+                    *y = x + 1.0;
+                }
+            }
+            for c in control_out.iter_mut() {
+                *c = 1.0;
+            }
+        }
+    }
+
+    #[test]
+    fn default_in_place_matches() {
+        let mut input = Buffer::default();
+        for (i, x) in input.get_mut().iter_mut().enumerate() {
+            *x = i as f32 * 0.1 - 1.0;
+        }
+        let ctrl = [1000f32.log2(), 6.0];
+        let mut out = [Buffer::default()];
+        Tilt::new(44_100.0).process(&ctrl, &mut [], &[&input], &mut out);
+        let mut buf = [Buffer::default()];
+        buf[0].get_mut().copy_from_slice(input.get());
+        Tilt::new(44_100.0).process_in_place(&ctrl, &mut buf);
+        assert_eq!(out[0].get(), buf[0].get());
+    }
+
+    #[test]
+    fn default_in_place_many_bufs() {
+        let mut buf: Vec<Buffer> = (0..20).map(|_| Buffer::default()).collect();
+        buf[19].get_mut()[3] = 2.0;
+        Wide.process_in_place(&[], &mut buf);
+        assert_eq!(buf[0].get()[0], 1.0);
+        assert_eq!(buf[19].get()[3], 3.0);
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! An additive synthesizer: a sum of sine harmonics at integer multiples of
+//! the fundamental, with amplitudes given per harmonic. Harmonics at or above
+//! Nyquist are skipped, so the output is free of aliasing.
+//!
+//! The control input is the fundamental frequency (log2 Hz).
+
+use std::f32::consts;
+
+use module::{Module, Buffer};
+
+pub struct Additive {
+    sr_offset: f32,
+    phase: f32,  // of the fundamental, in cycles, 0 to 1
+    amplitudes: Vec<f32>,
+}
+
+impl Additive {
+    /// Create an additive oscillator. `amplitudes[k]` is the amplitude of
+    /// harmonic `k + 1`, so the first entry is the fundamental.
+    pub fn new(sample_rate: f32, amplitudes: Vec<f32>) -> Additive {
+        Additive {
+            sr_offset: -sample_rate.log2(),
+            phase: 0.0,
+            amplitudes,
+        }
+    }
+}
+
+impl Module for Additive {
+    fn n_bufs_out(&self) -> usize { 1 }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        _buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        let freq = (control_in[0] + self.sr_offset).exp2();
+        let n_harmonics = ((0.5 / freq).ceil() as usize - 1).min(self.amplitudes.len());
+        let amplitudes = &self.amplitudes[..n_harmonics];
+        let out = buf_out[0].get_mut();
+        let mut phase = self.phase;
+        for i in 0..out.len() {
+            let th = 2.0 * consts::PI * phase;
+            let mut y = 0.0;
+            for (k, &a) in amplitudes.iter().enumerate() {
+                y += a * ((k + 1) as f32 * th).sin();
+            }
+            out[i] = y;
+            phase += freq;
+            phase -= phase.floor();
+        }
+        self.phase = phase;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts;
+
+    use modules::testutil::{render, amplitude};
+    use modules::Sin;
+    use super::Additive;
+
+    const SR: f32 = 44_100.0;
+
+    #[test]
+    fn single_harmonic_is_sin() {
+        let ctrl = [441f32.log2()];
+        let y = render(&mut Additive::new(SR, vec![1.0]), &ctrl, &[], 4096).remove(0);
+        let sin = render(&mut Sin::new(SR), &ctrl, &[], 4096).remove(0);
+        for (y, s) in y.iter().zip(&sin) {
+            assert!((y - s).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn band_limited_saw() {
+        let amplitudes = (1..80).map(|k| consts::FRAC_2_PI / k as f32).collect();
+        let f = 0.01;
+        let ctrl = [(f * SR).log2()];
+        let y = render(&mut Additive::new(SR, amplitudes), &ctrl, &[], 4000).remove(0);
+        // Harmonics up to 49 are present, falling off as 1/k. Any above
+        // Nyquist would alias onto these and spoil the match.
+        for &k in &[1, 2, 7, 30, 45, 49] {
+            let a = amplitude(&y, k as f32 * f);
+            assert!((a * k as f32 - consts::FRAC_2_PI).abs() < 0.01, "{} {}", k, a);
+        }
+    }
+}
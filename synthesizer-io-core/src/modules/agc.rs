@@ -0,0 +1,99 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! Automatic gain control: the gain is adjusted to keep the peak level of
+//! the output near a target.
+//!
+//! Control inputs are the target level (linear amplitude), the attack and
+//! release times in seconds (for the gain falling and rising, respectively),
+//! and the maximum gain (log2), which stops silence or noise being boosted
+//! without limit.
+
+use module::{Module, Buffer};
+use super::follower::{time_coef, EnvFollower};
+
+// Envelopes below this are treated as silence.
+const MIN_ENV: f32 = 1e-6;
+
+pub struct Agc {
+    sample_rate: f32,
+    env: EnvFollower,
+    gain: f32,
+}
+
+impl Agc {
+    pub fn new(sample_rate: f32) -> Agc {
+        Agc {
+            sample_rate,
+            env: EnvFollower::new(),
+            gain: 1.0,
+        }
+    }
+}
+
+impl Module for Agc {
+    fn n_bufs_out(&self) -> usize { 1 }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        let level = control_in[0];
+        let attack = time_coef(control_in[1], self.sample_rate);
+        let release = time_coef(control_in[2], self.sample_rate);
+        let max_gain = control_in[3].exp2();
+        let inb = buf_in[0].get();
+        let out = buf_out[0].get_mut();
+        for i in 0..out.len() {
+            // The detector catches peaks instantly and holds them over the
+            // release time; the gain smoothing supplies the attack.
+            let env = self.env.process(inb[i], 0.0, release).max(MIN_ENV);
+            let target = (level / env).min(max_gain);
+            let coef = if target < self.gain { attack } else { release };
+            self.gain = target + (self.gain - target) * coef;
+            out[i] = inb[i] * self.gain;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use modules::testutil::{render, sine};
+    use super::Agc;
+
+    fn peak(x: &[f32]) -> f32 {
+        x.iter().fold(0.0, |m, x| m.max(x.abs()))
+    }
+
+    #[test]
+    fn tracks_target() {
+        let sr = 44_100.0;
+        let ctrl = [0.5, 0.01, 0.5, 5.0];
+        let mut agc = Agc::new(sr);
+        let quiet = sine(100.0 / sr, 0.05, 88_192);
+        let y = render(&mut agc, &ctrl, &[&quiet], quiet.len()).remove(0);
+        // boosted toward the target, over about the release time
+        assert!(peak(&y[8820..13230]) < 0.4);
+        assert!((peak(&y[y.len() - 4410..]) - 0.5).abs() < 0.02);
+        // a loud input is attenuated, over about the attack time
+        let loud = sine(100.0 / sr, 2.0, 44_096);
+        let y = render(&mut agc, &ctrl, &[&loud], loud.len()).remove(0);
+        assert!(peak(&y[..441]) > 2.0);
+        assert!((peak(&y[4410..]) - 0.5).abs() < 0.02);
+        // silence doesn't blow up the gain
+        let silence = vec![0.0; 44_096];
+        render(&mut agc, &ctrl, &[&silence], silence.len());
+        assert!(agc.gain <= 32.0 + 1e-3);
+    }
+}
@@ -0,0 +1,100 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! A stereo auto-panner: pans a mono input left and right under the control
+//! of an internal LFO, using a constant-power pan law.
+//!
+//! The control inputs are the LFO rate (log2 of Hz), the depth (0 = center,
+//! 1 = sweeps hard left to hard right), and the LFO shape, blending from a
+//! sine at 0 to a triangle at 1.
+
+use std::f32::consts;
+
+use module::{Module, Buffer};
+
+pub struct AutoPan {
+    sr_offset: f32,
+    phase: f32,  // in cycles, 0 to 1
+}
+
+impl AutoPan {
+    pub fn new(sample_rate: f32) -> AutoPan {
+        AutoPan {
+            sr_offset: -sample_rate.log2(),
+            phase: 0.0,
+        }
+    }
+}
+
+/// Constant-power pan law. `pos` ranges from -1 (hard left) to 1 (hard right);
+/// returns the left and right gains.
+fn pan_gains(pos: f32) -> (f32, f32) {
+    let th = (pos.max(-1.0).min(1.0) + 1.0) * consts::FRAC_PI_4;
+    let (s, c) = th.sin_cos();
+    (c, s)
+}
+
+impl Module for AutoPan {
+    fn n_bufs_out(&self) -> usize { 2 }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        let freq = (control_in[0] + self.sr_offset).exp2();
+        let depth = control_in[1].max(0.0).min(1.0);
+        let shape = control_in[2].max(0.0).min(1.0);
+        let inb = buf_in[0].get();
+        let (out_l, out_r) = buf_out.split_at_mut(1);
+        let out_l = out_l[0].get_mut();
+        let out_r = out_r[0].get_mut();
+        let mut phase = self.phase;
+        for i in 0..out_l.len() {
+            let sine = (2.0 * consts::PI * phase).sin();
+            // triangle, in phase with the sine
+            let tri = 1.0 - 4.0 * (phase - 0.25 - (phase - 0.25).round()).abs();
+            let (l, r) = pan_gains(depth * (sine + shape * (tri - sine)));
+            out_l[i] = inb[i] * l;
+            out_r[i] = inb[i] * r;
+            phase += freq;
+        }
+        self.phase = phase - phase.floor();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use modules::testutil::render;
+    use super::AutoPan;
+
+    #[test]
+    fn pans_at_lfo_rate() {
+        let sr = 44_100.0;
+        for &shape in &[0.0, 1.0] {
+            let mut pan = AutoPan::new(sr);
+            let n = 44_096;
+            let y = render(&mut pan, &[2f32.log2(), 1.0, shape], &[&vec![1.0; n]], n);
+            let pos: Vec<f32> = (0..n).map(|i| y[1][i] - y[0][i]).collect();
+            // 2 Hz crosses center every quarter second; the one at 0 isn't counted
+            let crossings = (1..n).filter(|&i| (pos[i - 1] < 0.0) != (pos[i] < 0.0)).count();
+            assert_eq!(crossings, 3);
+            assert!(y[0].iter().any(|&l| l < 1e-3));
+            assert!(y[1].iter().any(|&r| r < 1e-3));
+            for i in 0..n {
+                let power = y[0][i] * y[0][i] + y[1][i] * y[1][i];
+                assert!((power - 1.0).abs() < 1e-5);
+            }
+        }
+    }
+}
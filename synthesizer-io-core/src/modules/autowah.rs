@@ -0,0 +1,110 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! An auto-wah (envelope filter): a resonant bandpass whose center frequency
+//! follows the envelope of the input, so louder playing sweeps the peak up
+//! (or, in the `Down` direction, down).
+//!
+//! Control inputs are the sensitivity (gain applied to the envelope), the
+//! base frequency (log2 Hz), the range of the sweep (in octaves), and the
+//! resonance (Q).
+
+use module::{Module, Buffer};
+use super::filters::Svf;
+use super::follower::{time_coef, EnvFollower};
+
+// Envelope follower times, in seconds.
+const ATTACK: f32 = 0.005;
+const RELEASE: f32 = 0.05;
+
+/// The direction the filter sweeps as the input gets louder.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WahDirection {
+    Up,
+    Down,
+}
+
+pub struct AutoWah {
+    sample_rate: f32,
+    direction: WahDirection,
+    attack: f32,
+    release: f32,
+    env: EnvFollower,
+    svf: Svf,
+}
+
+impl AutoWah {
+    pub fn new(sample_rate: f32, direction: WahDirection) -> AutoWah {
+        AutoWah {
+            sample_rate,
+            direction,
+            attack: time_coef(ATTACK, sample_rate),
+            release: time_coef(RELEASE, sample_rate),
+            env: EnvFollower::new(),
+            svf: Svf::new(),
+        }
+    }
+}
+
+impl Module for AutoWah {
+    fn n_bufs_out(&self) -> usize { 1 }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        let sensitivity = control_in[0];
+        let base = control_in[1];
+        let range = match self.direction {
+            WahDirection::Up => control_in[2],
+            WahDirection::Down => -control_in[2],
+        };
+        let k = control_in[3].max(0.5).recip();
+        let inb = buf_in[0].get();
+        let out = buf_out[0].get_mut();
+        for i in 0..out.len() {
+            let env = self.env.process(inb[i], self.attack, self.release);
+            let sweep = (env * sensitivity).min(1.0);
+            let g = Svf::coef((base + range * sweep).exp2(), self.sample_rate);
+            let (_, bp, _) = self.svf.process(inb[i], g, k);
+            // normalized to unity gain at the peak
+            out[i] = bp * k;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use modules::testutil::{render, sine, amplitude};
+    use super::{AutoWah, WahDirection};
+
+    // Gain at 2kHz, for an input at the given level.
+    fn gain(direction: WahDirection, level: f32) -> f32 {
+        let sr = 44_100.0;
+        let f = 2000.0 / sr;
+        let x = sine(f, level, 8192);
+        let ctrl = [1.0, 300f32.log2(), 3.0, 4.0];
+        let y = render(&mut AutoWah::new(sr, direction), &ctrl, &[&x], x.len()).remove(0);
+        amplitude(&y[4096..], f) / level
+    }
+
+    #[test]
+    fn follows_level() {
+        let loud = gain(WahDirection::Up, 1.0);
+        let quiet = gain(WahDirection::Up, 0.01);
+        assert!(loud > 0.5 && quiet < 0.1, "{} {}", loud, quiet);
+        // In the down direction, a loud signal closes the filter even further.
+        assert!(gain(WahDirection::Down, 1.0) < quiet);
+    }
+}
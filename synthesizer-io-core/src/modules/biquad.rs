@@ -13,6 +13,10 @@
 // limitations under the License.
 
 //! An implementation of biquad filters.
+//!
+//! Control inputs are the cutoff frequency (log2 Hz) and the resonance, from
+//! 0 (none) to 1 (self-oscillating). The response is lowpass, highpass or
+//! bandpass (with unity gain at the peak), chosen when it's created.
 
 
 use std::f32::consts;
@@ -21,16 +25,33 @@ use module::{Module, Buffer};
 
 pub struct Biquad {
     sr_offset: f32,
+    mode: FilterMode,
     state: [f32; 2],
     matrix: [f32; 16],
+    // control values the matrix was computed for
+    params: Option<(f32, f32)>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FilterMode {
+    Lowpass,
+    Highpass,
+    Bandpass,
 }
 
 impl Biquad {
+    /// A lowpass filter.
     pub fn new(sample_rate: f32) -> Biquad {
+        Biquad::with_mode(sample_rate, FilterMode::Lowpass)
+    }
+
+    pub fn with_mode(sample_rate: f32, mode: FilterMode) -> Biquad {
         Biquad {
             sr_offset: consts::PI.log2() - sample_rate.log2(),
+            mode,
             state: [0.0; 2],
             matrix: [0.0; 16],
+            params: None,
         }
     }
 }
@@ -42,17 +63,21 @@ struct StateParams {
     d: f32,
 }
 
+// The highest cutoff, as a fraction of the Nyquist frequency; tan(), below,
+// blows up at Nyquist.
+const MAX_CUTOFF: f32 = 0.98;
+
 // `log_f` is log2 of frequency relative to sampling rate, e.g.
 // -1.0 is the Nyquist frequency.
 fn calc_g(log_f: f32) -> f32 {
     // TODO: use lut to speed this up
     let f = log_f.exp2();  // pi has already been factored into sr_offset
-    f.tan()
+    f.min(MAX_CUTOFF * consts::FRAC_PI_2).tan()
 }
 
-// Compute parameters for low-pass state variable filter.
+// Compute parameters for state variable filter.
 // `res` ranges from 0 (no resonance) to 1 (self-oscillating)
-fn svf_lp(log_f: f32, res: f32) -> StateParams {
+fn svf(log_f: f32, res: f32, mode: FilterMode) -> StateParams {
     let g = calc_g(log_f);
     let k = 2.0 - 2.0 * res;
     let a1 = 2.0 / (1.0 + g * (g + k));
@@ -60,8 +85,16 @@ fn svf_lp(log_f: f32, res: f32) -> StateParams {
     let a3 = g * a2;
     let a = [a1 - 1.0, a2, -a2, 1.0 - a3];
     let b = [a2, a3];
-    let c = [0.5 * a2, 1.0 - 0.5 * a3];
-    let d = 0.5 * a3;
+    // The state and input give the band and low outputs, and the high output
+    // is what remains of the input.
+    let (c_bp, d_bp) = ([0.5 * a1, -0.5 * a2], 0.5 * a2);
+    let (c_lp, d_lp) = ([0.5 * a2, 1.0 - 0.5 * a3], 0.5 * a3);
+    let (c, d) = match mode {
+        FilterMode::Lowpass => (c_lp, d_lp),
+        FilterMode::Highpass => ([-k * c_bp[0] - c_lp[0], -k * c_bp[1] - c_lp[1]],
+            1.0 - k * d_bp - d_lp),
+        FilterMode::Bandpass => ([k * c_bp[0], k * c_bp[1]], k * d_bp),
+    };
     StateParams { a: a, b: b, c: c, d: d }
 }
 
@@ -84,14 +117,18 @@ fn raise_matrix(params: StateParams) -> [f32; 16] {
 impl Module for Biquad {
     fn n_bufs_out(&self) -> usize { 1 }
 
+    fn n_ctrl_in(&self) -> usize { 2 }
+
     fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
         buf_in: &[&Buffer], buf_out: &mut [Buffer])
     {
         let log_f = control_in[0];
         let res = control_in[1];
-        // TODO: maybe avoid recomputing matrix if params haven't changed
-        let params = svf_lp(log_f + self.sr_offset, res);
-        self.matrix = raise_matrix(params);
+        if self.params != Some((log_f, res)) {
+            let params = svf(log_f + self.sr_offset, res, self.mode);
+            self.matrix = raise_matrix(params);
+            self.params = Some((log_f, res));
+        }
         let inb = buf_in[0].get();
         let out = buf_out[0].get_mut();
         let m = &self.matrix;
@@ -115,3 +152,42 @@ impl Module for Biquad {
         self.state[1] = state1;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use modules::testutil::{render, sine, amplitude};
+    use super::{Biquad, FilterMode};
+
+    // Gain of the filter at `freq` Hz, with cutoff 1kHz and no resonance.
+    fn gain(mode: FilterMode, freq: f32) -> f32 {
+        let sr = 44_100.0;
+        let n = 8192;
+        let x = sine(freq / sr, 1.0, n);
+        let mut filter = Biquad::with_mode(sr, mode);
+        let y = render(&mut filter, &[1000f32.log2(), 0.0], &[&x], n).remove(0);
+        // skip the transient
+        amplitude(&y[4096..], freq / sr)
+    }
+
+    #[test]
+    fn modes() {
+        let lp = (gain(FilterMode::Lowpass, 100.0), gain(FilterMode::Lowpass, 10_000.0));
+        assert!(lp.0 > 0.95 && lp.1 < 0.02, "{:?}", lp);
+        let hp = (gain(FilterMode::Highpass, 100.0), gain(FilterMode::Highpass, 10_000.0));
+        assert!(hp.0 < 0.02 && hp.1 > 0.95, "{:?}", hp);
+        let bp = gain(FilterMode::Bandpass, 1000.0);
+        assert!((bp - 1.0).abs() < 0.02, "{}", bp);
+        // with no resonance, the bandpass is broad
+        assert!(gain(FilterMode::Bandpass, 100.0) < 0.25);
+        assert!(gain(FilterMode::Bandpass, 10_000.0) < 0.25);
+    }
+
+    #[test]
+    fn cutoff_above_nyquist() {
+        let sr = 44_100.0;
+        let x = sine(0.1, 1.0, 1024);
+        let mut filter = Biquad::new(sr);
+        let y = render(&mut filter, &[30_000f32.log2(), 0.5], &[&x], 1024).remove(0);
+        assert!(y.iter().all(|y| y.is_finite() && y.abs() < 10.0));
+    }
+}
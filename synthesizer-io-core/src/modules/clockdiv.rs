@@ -0,0 +1,131 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! A clock divider and multiplier, for polyrhythmic sequencing.
+//!
+//! The input is a clock of triggers or gates; rising edges are counted. The
+//! output is a one-sample pulse of 1.0 on every Nth input edge, where N is
+//! the division (control input 0). With a multiplication M (control input 1)
+//! greater than 1, a further M - 1 pulses are spaced evenly until the next
+//! expected output pulse, predicted from the last interval between input
+//! edges. Both controls are rounded to whole numbers, with a minimum of 1.
+
+use module::{Module, Buffer};
+use super::edge_detect::{Edge, EdgeDetector};
+
+const THRESHOLD: f32 = 0.5;
+const HYSTERESIS: f32 = 0.1;
+
+pub struct ClockDiv {
+    detector: EdgeDetector,
+    // input edges seen since the last divided pulse
+    count: usize,
+    // samples since the last input edge
+    since_edge: usize,
+    // last interval between input edges, in samples
+    interval: Option<usize>,
+    // samples since the last divided pulse
+    since_out: f32,
+    sub_interval: f32,
+    sub_remaining: usize,
+}
+
+impl ClockDiv {
+    pub fn new() -> ClockDiv {
+        ClockDiv {
+            detector: EdgeDetector::new(),
+            count: 0,
+            since_edge: 0,
+            interval: None,
+            since_out: 0.0,
+            sub_interval: 0.0,
+            sub_remaining: 0,
+        }
+    }
+
+    fn process_sample(&mut self, x: f32, div: usize, mult: usize) -> bool {
+        self.since_edge += 1;
+        self.since_out += 1.0;
+        if self.detector.process(x, THRESHOLD, HYSTERESIS) == Some(Edge::Rising) {
+            if self.count > 0 || self.interval.is_some() {
+                self.interval = Some(self.since_edge);
+            }
+            self.since_edge = 0;
+            if self.count % div == 0 {
+                self.count = 1;
+                self.since_out = 0.0;
+                self.sub_remaining = 0;
+                if let Some(interval) = self.interval {
+                    self.sub_interval = (interval * div) as f32 / mult as f32;
+                    self.sub_remaining = mult - 1;
+                }
+                return true;
+            }
+            self.count += 1;
+        }
+        if self.sub_remaining > 0 && self.since_out >= self.sub_interval {
+            self.since_out -= self.sub_interval;
+            self.sub_remaining -= 1;
+            return true;
+        }
+        false
+    }
+}
+
+impl Module for ClockDiv {
+    fn n_bufs_out(&self) -> usize { 1 }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        let div = control_in[0].round().max(1.0) as usize;
+        let mult = control_in[1].round().max(1.0) as usize;
+        let inb = buf_in[0].get();
+        let out = buf_out[0].get_mut();
+        for i in 0..out.len() {
+            out[i] = if self.process_sample(inb[i], div, mult) { 1.0 } else { 0.0 };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use modules::testutil::render;
+    use super::ClockDiv;
+
+    // A clock with a 10 sample gate every 100 samples, starting at sample 50.
+    fn clock(n: usize) -> Vec<f32> {
+        (0..n).map(|i| if i >= 50 && (i - 50) % 100 < 10 { 1.0 } else { 0.0 }).collect()
+    }
+
+    fn pulses(div: f32, mult: f32) -> Vec<usize> {
+        let x = clock(2048);
+        let y = render(&mut ClockDiv::new(), &[div, mult], &[&x], x.len());
+        (0..y[0].len()).filter(|&i| y[0][i] == 1.0).collect()
+    }
+
+    #[test]
+    fn divide_by_four() {
+        assert_eq!(pulses(4.0, 1.0), vec![50, 450, 850, 1250, 1650]);
+    }
+
+    #[test]
+    fn multiply_by_two() {
+        // Multiplication starts once an interval has been measured.
+        let p = pulses(1.0, 2.0);
+        assert_eq!(&p[..5], &[50, 150, 200, 250, 300]);
+        assert_eq!(p.len(), 20 + 19);
+    }
+}
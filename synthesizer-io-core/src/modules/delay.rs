@@ -0,0 +1,54 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! A delay line with fractional (linearly interpolated) reads, shared by the
+//! delay-based modules.
+
+pub struct DelayLine {
+    buf: Vec<f32>,
+    // index of the most recently written sample
+    pos: usize,
+}
+
+impl DelayLine {
+    /// Create a delay line that can delay by up to `max_delay` samples.
+    /// This allocates, so should be done outside the real-time thread.
+    pub fn new(max_delay: usize) -> DelayLine {
+        DelayLine {
+            buf: vec![0.0; max_delay + 2],
+            pos: 0,
+        }
+    }
+
+    pub fn write(&mut self, x: f32) {
+        self.pos += 1;
+        if self.pos == self.buf.len() {
+            self.pos = 0;
+        }
+        self.buf[self.pos] = x;
+    }
+
+    /// Read the sample written `delay` samples ago; a delay of 0 is the most
+    /// recently written sample. The delay is clamped to the valid range.
+    pub fn read(&self, delay: f32) -> f32 {
+        let n = self.buf.len();
+        let delay = delay.max(0.0).min((n - 2) as f32);
+        let d_int = delay as usize;
+        let frac = delay - d_int as f32;
+        let i0 = (self.pos + n - d_int) % n;
+        let i1 = if i0 == 0 { n - 1 } else { i0 - 1 };
+        self.buf[i0] + frac * (self.buf[i1] - self.buf[i0])
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! A drum voice: a sine with a downward pitch sweep for the body, plus a
+//! noise burst for the transient, both started by the rising edge of a gate
+//! (the buffer input).
+//!
+//! Control inputs are the base pitch (log2 Hz), the pitch sweep (in octaves
+//! above the base at the onset), the decay time of the body (in seconds), and
+//! the noise amount. Low pitches with a wide sweep give kicks, higher pitches
+//! with more noise give snares and toms.
+
+use std::f32::consts;
+
+use module::{Module, Buffer};
+use super::edge_detect::{Edge, EdgeDetector};
+use super::follower::time_coef;
+use super::rng::Rng;
+
+const THRESHOLD: f32 = 0.5;
+const HYSTERESIS: f32 = 0.1;
+
+// Time constants of the pitch and noise envelopes, in seconds.
+const PITCH_DECAY: f32 = 0.03;
+const NOISE_DECAY: f32 = 0.02;
+
+pub struct Drum {
+    sample_rate: f32,
+    sr_offset: f32,
+    pitch_coef: f32,
+    noise_coef: f32,
+    detector: EdgeDetector,
+    phase: f32,
+    amp_env: f32,
+    pitch_env: f32,
+    noise_env: f32,
+    rng: Rng,
+}
+
+impl Drum {
+    pub fn new(sample_rate: f32) -> Drum {
+        Drum {
+            sample_rate,
+            sr_offset: -sample_rate.log2(),
+            pitch_coef: time_coef(PITCH_DECAY, sample_rate),
+            noise_coef: time_coef(NOISE_DECAY, sample_rate),
+            detector: EdgeDetector::new(),
+            phase: 0.0,
+            amp_env: 0.0,
+            pitch_env: 0.0,
+            noise_env: 0.0,
+            rng: Rng::new(1),
+        }
+    }
+}
+
+impl Module for Drum {
+    fn n_bufs_out(&self) -> usize { 1 }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        let base = control_in[0] + self.sr_offset;
+        let sweep = control_in[1];
+        let amp_coef = time_coef(control_in[2], self.sample_rate);
+        let noise_amt = control_in[3];
+        let gate = buf_in[0].get();
+        let out = buf_out[0].get_mut();
+        for i in 0..out.len() {
+            if self.detector.process(gate[i], THRESHOLD, HYSTERESIS) == Some(Edge::Rising) {
+                self.phase = 0.0;
+                self.amp_env = 1.0;
+                self.pitch_env = 1.0;
+                self.noise_env = 1.0;
+            }
+            let freq = (base + sweep * self.pitch_env).exp2();
+            let body = self.amp_env * (2.0 * consts::PI * self.phase).sin();
+            let noise = noise_amt * self.noise_env * self.rng.bipolar();
+            out[i] = body + noise;
+            self.phase += freq;
+            self.phase -= self.phase.floor();
+            self.amp_env *= amp_coef;
+            self.pitch_env *= self.pitch_coef;
+            self.noise_env *= self.noise_coef;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use modules::testutil::render;
+    use super::Drum;
+
+    fn rms(x: &[f32]) -> f32 {
+        (x.iter().map(|x| x * x).sum::<f32>() / x.len() as f32).sqrt()
+    }
+
+    fn zero_crossings(x: &[f32]) -> usize {
+        x.windows(2).filter(|w| (w[0] < 0.0) != (w[1] < 0.0)).count()
+    }
+
+    #[test]
+    fn kick() {
+        let sr = 44_100.0;
+        let n = 22_048;
+        let gate: Vec<f32> = (0..n).map(|i| if i >= 1000 && i < 2000 { 1.0 } else { 0.0 }).collect();
+        let ctrl = [50f32.log2(), 3.0, 0.2, 0.0];
+        let body = render(&mut Drum::new(sr), &ctrl, &[&gate], n).remove(0);
+        let ctrl = [ctrl[0], ctrl[1], ctrl[2], 1.0];
+        let both = render(&mut Drum::new(sr), &ctrl, &[&gate], n).remove(0);
+        assert!(body[..1000].iter().all(|&x| x == 0.0));
+        // decaying tone
+        let (a, b, c) = (&body[2000..6000], &body[10000..14000], &body[18000..22000]);
+        assert!(rms(a) > 1.5 * rms(b) && rms(b) > 1.5 * rms(c));
+        // the pitch sweeps down: 4000 samples at 50Hz has about 9 crossings
+        assert!(zero_crossings(&body[1000..5000]) > 15);
+        assert!(zero_crossings(&body[10000..14000]) < 11);
+        // the noise is only a transient
+        let noise: Vec<f32> = both.iter().zip(&body).map(|(x, y)| x - y).collect();
+        assert!(rms(&noise[1000..1500]) > 0.2);
+        assert!(rms(&noise[8000..]) < 0.001);
+    }
+}
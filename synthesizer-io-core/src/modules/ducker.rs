@@ -0,0 +1,103 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! A sidechain ducker: attenuates the main input whenever the key input is
+//! above a threshold.
+//!
+//! Buffer inputs are the main signal and the key. Control inputs are the
+//! threshold (linear amplitude), depth (0 = no ducking, 1 = full mute), and
+//! attack and release times in seconds.
+
+use module::{Module, Buffer};
+use super::follower::{time_coef, EnvFollower};
+
+pub struct Ducker {
+    sample_rate: f32,
+    key_env: EnvFollower,
+    gain: f32,
+}
+
+impl Ducker {
+    pub fn new(sample_rate: f32) -> Ducker {
+        Ducker {
+            sample_rate,
+            key_env: EnvFollower::new(),
+            gain: 1.0,
+        }
+    }
+}
+
+impl Module for Ducker {
+    fn n_bufs_out(&self) -> usize { 1 }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        let thresh = control_in[0];
+        let ducked = 1.0 - control_in[1].max(0.0).min(1.0);
+        let attack = time_coef(control_in[2], self.sample_rate);
+        let release = time_coef(control_in[3], self.sample_rate);
+        let main = buf_in[0].get();
+        let key = buf_in[1].get();
+        let out = buf_out[0].get_mut();
+        for i in 0..out.len() {
+            // The key detector attacks instantly, so that the gate opens on
+            // the first loud sample; the gain smoothing supplies the attack.
+            let env = self.key_env.process(key[i], 0.0, release);
+            let target = if env > thresh { ducked } else { 1.0 };
+            let coef = if target < self.gain { attack } else { release };
+            self.gain = target + (self.gain - target) * coef;
+            out[i] = main[i] * self.gain;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+    use module::{Module, Buffer};
+    use super::Ducker;
+
+    // Run the ducker on a constant main signal for `n_chunks`, with the key
+    // either silent or a loud 100Hz sine, returning the last output sample.
+    fn run(ducker: &mut Ducker, loud: bool, n_chunks: usize) -> f32 {
+        let ctrl = [0.1, 0.8, 0.005, 0.05];
+        let mut main = Buffer::default();
+        for x in main.get_mut().iter_mut() {
+            *x = 1.0;
+        }
+        let mut key = Buffer::default();
+        let mut out = [Buffer::default()];
+        for chunk in 0..n_chunks {
+            for (i, x) in key.get_mut().iter_mut().enumerate() {
+                let t = (chunk * 32 + i) as f32 / 44_100.0;
+                *x = if loud { (2.0 * PI * 100.0 * t).sin() } else { 0.0 };
+            }
+            ducker.process(&ctrl, &mut [], &[&main, &key], &mut out);
+        }
+        out[0].get()[31]
+    }
+
+    #[test]
+    fn ducks_and_recovers() {
+        let mut ducker = Ducker::new(44_100.0);
+        let quiet = run(&mut ducker, false, 100);
+        assert!((quiet - 1.0).abs() < 1e-3);
+        let ducked = run(&mut ducker, true, 300);
+        assert!((ducked - 0.2).abs() < 0.01, "ducked level {}", ducked);
+        let recovered = run(&mut ducker, false, 1500);
+        assert!((recovered - 1.0).abs() < 0.01, "recovered level {}", recovered);
+    }
+}
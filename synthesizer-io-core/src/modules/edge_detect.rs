@@ -0,0 +1,120 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! Edge detection, for turning a (possibly noisy) gate into clean triggers.
+//!
+//! `EdgeDetector` is the reusable core, for modules that need to respond to
+//! gate edges. `EdgeDetect` wraps it as a module: it outputs a one-sample
+//! pulse of 1.0 on each edge of the selected direction. Control inputs are
+//! the threshold and the hysteresis width, centered on the threshold.
+
+use module::{Module, Buffer};
+
+/// The direction of a gate transition.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Edge {
+    Rising,
+    Falling,
+}
+
+/// Schmitt-trigger style edge detection. The input must rise above
+/// `threshold + hysteresis / 2` to go high, and fall below
+/// `threshold - hysteresis / 2` to go low again.
+pub struct EdgeDetector {
+    high: bool,
+}
+
+impl EdgeDetector {
+    pub fn new() -> EdgeDetector {
+        EdgeDetector { high: false }
+    }
+
+    /// Whether the gate is currently considered high.
+    pub fn is_high(&self) -> bool {
+        self.high
+    }
+
+    /// Process one sample, reporting an edge if one occurred.
+    pub fn process(&mut self, x: f32, threshold: f32, hysteresis: f32) -> Option<Edge> {
+        let half = 0.5 * hysteresis.max(0.0);
+        if !self.high && x > threshold + half {
+            self.high = true;
+            Some(Edge::Rising)
+        } else if self.high && x < threshold - half {
+            self.high = false;
+            Some(Edge::Falling)
+        } else {
+            None
+        }
+    }
+}
+
+pub struct EdgeDetect {
+    edge: Edge,
+    detector: EdgeDetector,
+}
+
+impl EdgeDetect {
+    pub fn new(edge: Edge) -> EdgeDetect {
+        EdgeDetect {
+            edge,
+            detector: EdgeDetector::new(),
+        }
+    }
+}
+
+impl Module for EdgeDetect {
+    fn n_bufs_out(&self) -> usize { 1 }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        let threshold = control_in[0];
+        let hysteresis = control_in[1];
+        let inb = buf_in[0].get();
+        let out = buf_out[0].get_mut();
+        for i in 0..out.len() {
+            let edge = self.detector.process(inb[i], threshold, hysteresis);
+            out[i] = if edge == Some(self.edge) { 1.0 } else { 0.0 };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+    use modules::testutil::render;
+    use super::{EdgeDetect, Edge};
+
+    // Count the pulses from a slow inverted cosine with noise added, for 10 periods.
+    fn count_edges(edge: Edge, hysteresis: f32) -> usize {
+        let mut seed = 1u32;
+        let x: Vec<f32> = (0..10_016).map(|i| {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let noise = 0.1 * ((seed >> 8) as f32 / (1 << 23) as f32 - 1.0);
+            noise - (2.0 * PI * 0.001 * i as f32).cos()
+        }).collect();
+        let y = render(&mut EdgeDetect::new(edge), &[0.0, hysteresis], &[&x], x.len());
+        y[0].iter().filter(|&&y| y == 1.0).count()
+    }
+
+    #[test]
+    fn one_trigger_per_crossing() {
+        assert_eq!(count_edges(Edge::Rising, 0.3), 10);
+        assert_eq!(count_edges(Edge::Falling, 0.3), 10);
+        // the noise is enough to cause spurious triggers without hysteresis
+        assert!(count_edges(Edge::Rising, 0.0) > 10);
+    }
+}
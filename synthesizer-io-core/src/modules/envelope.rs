@@ -0,0 +1,174 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! An ADSR envelope at audio rate, driven by a gate, for multiplying with
+//! an oscillator. Unlike `Adsr`, which follows notes and produces a log2
+//! gain as a control, this produces a linear level in a buffer.
+//!
+//! Control inputs are the gate (on above 0.5), the attack, decay and
+//! release times in seconds, and the sustain level (0..1). Each stage is a
+//! linear ramp. When the gate opens, the attack starts from the current
+//! level, so retriggering during the release doesn't click.
+
+use module::{Module, Buffer};
+
+pub struct Envelope {
+    sample_rate: f32,
+    value: f32,
+    state: State,
+    // level change per sample in the release, set when it starts
+    release_step: f32,
+}
+
+#[derive(Clone, Copy)]
+enum State {
+    Idle,
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+use self::State::*;
+
+impl Envelope {
+    pub fn new(sample_rate: f32) -> Envelope {
+        Envelope {
+            sample_rate,
+            value: 0.0,
+            state: Idle,
+            release_step: 0.0,
+        }
+    }
+
+    // The change per sample to cover `distance` in `secs`, or None if the
+    // stage is shorter than a sample and should be skipped.
+    fn step(&self, distance: f32, secs: f32) -> Option<f32> {
+        let n = secs * self.sample_rate;
+        if n > 1.0 {
+            Some(distance / n)
+        } else {
+            None
+        }
+    }
+}
+
+impl Module for Envelope {
+    fn n_bufs_out(&self) -> usize { 1 }
+
+    fn n_ctrl_in(&self) -> usize { 5 }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        _buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        let gate = control_in[0] > 0.5;
+        let attack = control_in[1];
+        let decay = control_in[2];
+        let sustain = control_in[3].max(0.0).min(1.0);
+        let release = control_in[4];
+        match (gate, self.state) {
+            (true, Idle) | (true, Release) => self.state = Attack,
+            (false, Attack) | (false, Decay) | (false, Sustain) => {
+                self.state = Release;
+                self.release_step = self.step(self.value, release).unwrap_or(self.value);
+            }
+            _ => (),
+        }
+        let attack_step = self.step(1.0, attack);
+        let decay_step = self.step(1.0 - sustain, decay);
+        let out = buf_out[0].get_mut();
+        for i in 0..out.len() {
+            // Zero-length stages are skipped within the same sample.
+            loop {
+                match self.state {
+                    Idle => (),
+                    Attack => match attack_step {
+                        Some(step) if self.value + step < 1.0 => self.value += step,
+                        Some(_) => {
+                            self.value = 1.0;
+                            self.state = Decay;
+                        }
+                        None => {
+                            self.value = 1.0;
+                            self.state = Decay;
+                            continue;
+                        }
+                    },
+                    Decay => match decay_step {
+                        Some(step) if self.value - step > sustain => self.value -= step,
+                        Some(_) => {
+                            self.value = sustain;
+                            self.state = Sustain;
+                        }
+                        None => {
+                            self.value = sustain;
+                            self.state = Sustain;
+                            continue;
+                        }
+                    },
+                    // follow changes in the sustain level
+                    Sustain => self.value = sustain,
+                    Release => if self.value > self.release_step {
+                        self.value -= self.release_step;
+                    } else {
+                        self.value = 0.0;
+                        self.state = Idle;
+                    },
+                }
+                break;
+            }
+            out[i] = self.value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use modules::testutil::render;
+    use super::Envelope;
+
+    #[test]
+    fn stages() {
+        let sr = 1000.0;
+        let mut env = Envelope::new(sr);
+        // attack 64ms, decay 64ms, sustain 0.5, release 128ms
+        let on = [1.0, 0.064, 0.064, 0.5, 0.128];
+        let off = [0.0, 0.064, 0.064, 0.5, 0.128];
+        let y = render(&mut env, &on, &[], 256).remove(0);
+        assert!((y[31] - 0.5).abs() < 0.02);
+        assert!((y[63] - 1.0).abs() < 0.02);
+        assert!((y[95] - 0.75).abs() < 0.02);
+        assert!(y[160..].iter().all(|&x| x == 0.5));
+        let y = render(&mut env, &off, &[], 160).remove(0);
+        assert!((y[63] - 0.25).abs() < 0.02);
+        assert!(y[128..].iter().all(|&x| x == 0.0));
+
+        // retrigger partway through the release
+        render(&mut env, &on, &[], 256);
+        let mut y = render(&mut env, &off, &[], 64).remove(0);
+        y.extend(render(&mut env, &on, &[], 32).remove(0));
+        assert!(y.windows(2).all(|w| (w[1] - w[0]).abs() < 0.02));
+        assert!(y[64] > y[63]);
+    }
+
+    #[test]
+    fn zero_length_stages() {
+        let mut env = Envelope::new(44_100.0);
+        let y = render(&mut env, &[1.0, 0.0, 0.0, 0.3, 0.0], &[], 32).remove(0);
+        assert!(y.iter().all(|&x| x == 0.3));
+        let y = render(&mut env, &[0.0, 0.0, 0.0, 0.3, 0.0], &[], 32).remove(0);
+        assert!(y.iter().all(|&x| x == 0.0));
+    }
+}
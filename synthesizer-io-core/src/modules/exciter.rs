@@ -0,0 +1,96 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! An exciter (harmonic enhancer): the signal above a cutoff frequency is
+//! run through a waveshaper, and the resulting harmonics are mixed back in
+//! to add presence and air.
+//!
+//! The control inputs are the highpass cutoff (log2 of Hz) and the amount
+//! of the generated signal to mix in (0 to 1, a little goes a long way).
+
+use module::{Module, Buffer};
+use super::filters::{OnePole, DcBlocker};
+
+pub struct Exciter {
+    sample_rate: f32,
+    hp1: OnePole,
+    hp2: OnePole,
+    dc: DcBlocker,
+}
+
+impl Exciter {
+    pub fn new(sample_rate: f32) -> Exciter {
+        Exciter {
+            sample_rate,
+            hp1: OnePole::new(),
+            hp2: OnePole::new(),
+            dc: DcBlocker::new(),
+        }
+    }
+}
+
+// Mostly odd harmonics from the soft clipping, plus even harmonics (and DC,
+// removed afterwards) from the square term.
+fn shape(x: f32) -> f32 {
+    (3.0 * x).tanh() * (1.0 / 3.0) + x * x
+}
+
+impl Module for Exciter {
+    fn n_bufs_out(&self) -> usize { 1 }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        let coef = OnePole::coef(control_in[0].exp2(), self.sample_rate);
+        let amount = control_in[1];
+        let inb = buf_in[0].get();
+        let out = buf_out[0].get_mut();
+        for i in 0..out.len() {
+            let x = inb[i];
+            // two one-pole highpasses, for 12dB/octave
+            let h = x - self.hp1.process(x, coef);
+            let h = h - self.hp2.process(h, coef);
+            out[i] = x + amount * self.dc.process(shape(h));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use modules::testutil::{render, sine, amplitude};
+    use super::Exciter;
+
+    #[test]
+    fn adds_high_harmonics() {
+        let sr = 48_000.0;
+        let n = 8192;
+        let (f_lo, f_hi) = (9.0 / 4096.0, 256.0 / 4096.0);
+        let lo = sine(f_lo, 0.5, n);
+        let hi = sine(f_hi, 0.3, n);
+        let x: Vec<f32> = lo.iter().zip(&hi).map(|(a, b)| a + b).collect();
+        let mut exciter = Exciter::new(sr);
+        let y = render(&mut exciter, &[2000f32.log2(), 0.5], &[&x], n).remove(0);
+        let (x, y) = (&x[4096..], &y[4096..]);
+        // low band unchanged
+        assert!((amplitude(y, f_lo) - 0.5).abs() < 0.01);
+        assert!(amplitude(y, 2.0 * f_lo) < 1e-3);
+        // harmonics of the high band added
+        for k in 2..4 {
+            let f = k as f32 * f_hi;
+            assert!(amplitude(x, f) < 1e-4);
+            assert!(amplitude(y, f) > 0.001, "harmonic {}: {}", k, amplitude(y, f));
+        }
+    }
+}
@@ -0,0 +1,96 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! Simple filters shared by several modules.
+
+use std::f32::consts;
+
+/// A one-pole lowpass in topology-preserving form. The highpass output is
+/// the input minus the lowpass output.
+pub struct OnePole {
+    state: f32,
+}
+
+impl OnePole {
+    pub fn new() -> OnePole {
+        OnePole { state: 0.0 }
+    }
+
+    /// Compute the coefficient for a cutoff given in Hz. The cutoff is clamped
+    /// to just below Nyquist.
+    pub fn coef(freq: f32, sample_rate: f32) -> f32 {
+        let g = (consts::PI * (freq / sample_rate).min(0.49)).tan();
+        g / (1.0 + g)
+    }
+
+    /// Process one sample, returning the lowpass output.
+    pub fn process(&mut self, x: f32, coef: f32) -> f32 {
+        let v = (x - self.state) * coef;
+        let lp = v + self.state;
+        self.state = lp + v;
+        lp
+    }
+}
+
+/// A state variable filter in topology-preserving form, giving simultaneous
+/// lowpass, bandpass, and highpass outputs.
+pub struct Svf {
+    s1: f32,
+    s2: f32,
+}
+
+impl Svf {
+    pub fn new() -> Svf {
+        Svf { s1: 0.0, s2: 0.0 }
+    }
+
+    /// Compute the frequency coefficient for a cutoff given in Hz. The cutoff is
+    /// clamped to just below Nyquist.
+    pub fn coef(freq: f32, sample_rate: f32) -> f32 {
+        (consts::PI * (freq / sample_rate).min(0.49)).tan()
+    }
+
+    /// Process one sample, given the frequency coefficient `g` and damping `k`
+    /// (1/Q). Returns the lowpass, bandpass, and highpass outputs.
+    pub fn process(&mut self, x: f32, g: f32, k: f32) -> (f32, f32, f32) {
+        let hp = (x - (k + g) * self.s1 - self.s2) / (1.0 + g * (g + k));
+        let v1 = g * hp;
+        let bp = v1 + self.s1;
+        self.s1 = bp + v1;
+        let v2 = g * bp;
+        let lp = v2 + self.s2;
+        self.s2 = lp + v2;
+        (lp, bp, hp)
+    }
+}
+
+/// A DC blocking filter (a one-zero, one-pole highpass at a few Hz).
+pub struct DcBlocker {
+    x1: f32,
+    y1: f32,
+}
+
+impl DcBlocker {
+    pub fn new() -> DcBlocker {
+        DcBlocker { x1: 0.0, y1: 0.0 }
+    }
+
+    pub fn process(&mut self, x: f32) -> f32 {
+        let y = x - self.x1 + 0.995 * self.y1;
+        self.x1 = x;
+        self.y1 = y;
+        y
+    }
+}
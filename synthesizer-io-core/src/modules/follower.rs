@@ -0,0 +1,45 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! Envelope followers and smoothing helpers shared by the dynamics modules.
+
+/// Compute the coefficient of a one-pole smoother with time constant `t`
+/// in seconds. A zero or negative time gives an instantaneous response.
+pub fn time_coef(t: f32, sample_rate: f32) -> f32 {
+    if t > 0.0 {
+        (-1.0 / (t * sample_rate)).exp()
+    } else {
+        0.0
+    }
+}
+
+/// A peak envelope follower with separate attack and release coefficients
+/// (as computed by `time_coef`).
+pub struct EnvFollower {
+    env: f32,
+}
+
+impl EnvFollower {
+    pub fn new() -> EnvFollower {
+        EnvFollower { env: 0.0 }
+    }
+
+    pub fn process(&mut self, x: f32, attack: f32, release: f32) -> f32 {
+        let x = x.abs();
+        let coef = if x > self.env { attack } else { release };
+        self.env = x + (self.env - x) * coef;
+        self.env
+    }
+}
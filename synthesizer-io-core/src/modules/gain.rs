@@ -35,16 +35,44 @@ impl Module for Gain {
     fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
         buf_in: &[&Buffer], buf_out: &mut [Buffer])
     {
+        buf_out[0].get_mut().copy_from_slice(buf_in[0].get());
+        self.process_in_place(control_in, buf_out);
+    }
+
+    fn process_in_place(&mut self, control_in: &[f32], buf: &mut [Buffer]) {
         let ctrl = control_in[0];
         let g = ctrl.exp2();
-        let out = buf_out[0].get_mut();
-        let dg = (g - self.last_g) * (1.0 / out.len() as f32);
+        let buf = buf[0].get_mut();
+        let dg = (g - self.last_g) * (1.0 / buf.len() as f32);
         let mut y = self.last_g + dg;
         self.last_g = g;
-        let buf = buf_in[0].get();
-        for i in 0..out.len() {
-            out[i] = buf[i] * y;
+        for i in 0..buf.len() {
+            buf[i] *= y;
             y += dg;
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use module::{Module, Buffer};
+    use super::Gain;
+
+    #[test]
+    fn in_place_matches() {
+        let mut input = Buffer::default();
+        for (i, x) in input.get_mut().iter_mut().enumerate() {
+            *x = i as f32 * 0.1 - 1.0;
+        }
+        let mut gain = Gain::new();
+        let mut gain_ip = Gain::new();
+        let mut out = [Buffer::default()];
+        for &g in &[-1.0, 0.5, 0.5] {
+            gain.process(&[g], &mut [], &[&input], &mut out);
+            let mut buf = [Buffer::default()];
+            buf[0].get_mut().copy_from_slice(input.get());
+            gain_ip.process_in_place(&[g], &mut buf);
+            assert_eq!(out[0].get(), buf[0].get());
+        }
+    }
+}
@@ -0,0 +1,150 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! A rotary speaker (Leslie) simulation.
+//!
+//! A crossover splits the (mono) input: the highs go to a rotating horn and
+//! the lows to a rotating drum. Each rotor modulates amplitude and, through a
+//! modulated delay, pitch (the Doppler effect). The two outputs are picked
+//! up from opposite sides of the cabinet, giving a stereo image.
+//!
+//! The control input is the speed, from 0 (slow, "chorale") to 1 (fast,
+//! "tremolo"). The rotors accelerate and decelerate gradually toward the
+//! selected speed, the heavy drum more slowly than the horn.
+
+use std::f32::consts;
+
+use module::{Module, Buffer};
+use super::delay::DelayLine;
+use super::filters::OnePole;
+use super::follower::time_coef;
+
+const CROSSOVER: f32 = 800.0;
+
+// Center delay, in seconds. The excursion of each rotor must be less.
+const CENTER_DELAY: f32 = 0.001;
+
+struct Rotor {
+    slow: f32,  // rates in Hz
+    fast: f32,
+    accel: f32,  // smoothing coefficient for the rate
+    rate: f32,
+    phase: f32,  // in cycles, 0 to 1
+    depth: f32,  // delay excursion, in samples
+    am: f32,  // amplitude modulation depth
+    delay: DelayLine,
+}
+
+impl Rotor {
+    fn new(sample_rate: f32, slow: f32, fast: f32, accel_time: f32, excursion: f32, am: f32)
+        -> Rotor
+    {
+        let max_delay = (2.0 * CENTER_DELAY * sample_rate).ceil() as usize;
+        Rotor {
+            slow,
+            fast,
+            accel: time_coef(accel_time, sample_rate),
+            rate: slow,
+            phase: 0.0,
+            depth: excursion * sample_rate,
+            am,
+            delay: DelayLine::new(max_delay),
+        }
+    }
+
+    // Process one sample, returning the left and right pickups.
+    fn process(&mut self, x: f32, speed: f32, center: f32, sr_recip: f32) -> (f32, f32) {
+        let target = self.slow + speed * (self.fast - self.slow);
+        self.rate = target + (self.rate - target) * self.accel;
+        self.phase += self.rate * sr_recip;
+        self.phase -= self.phase.floor();
+        self.delay.write(x);
+        let s = (2.0 * consts::PI * self.phase).sin();
+        let left = self.delay.read(center - self.depth * s) * (1.0 + self.am * s);
+        let right = self.delay.read(center + self.depth * s) * (1.0 - self.am * s);
+        (left, right)
+    }
+}
+
+pub struct Leslie {
+    sr_recip: f32,
+    center: f32,  // in samples
+    crossover_coef: f32,
+    crossover: OnePole,
+    horn: Rotor,
+    drum: Rotor,
+}
+
+impl Leslie {
+    pub fn new(sample_rate: f32) -> Leslie {
+        Leslie {
+            sr_recip: sample_rate.recip(),
+            center: CENTER_DELAY * sample_rate,
+            crossover_coef: OnePole::coef(CROSSOVER, sample_rate),
+            crossover: OnePole::new(),
+            horn: Rotor::new(sample_rate, 0.8, 6.8, 0.6, 0.0004, 0.5),
+            drum: Rotor::new(sample_rate, 0.7, 5.9, 3.0, 0.0002, 0.2),
+        }
+    }
+}
+
+impl Module for Leslie {
+    fn n_bufs_out(&self) -> usize { 2 }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        let speed = control_in[0].max(0.0).min(1.0);
+        let inb = buf_in[0].get();
+        let (left, right) = buf_out.split_at_mut(1);
+        let left = left[0].get_mut();
+        let right = right[0].get_mut();
+        for i in 0..left.len() {
+            let low = self.crossover.process(inb[i], self.crossover_coef);
+            let high = inb[i] - low;
+            let (hl, hr) = self.horn.process(high, speed, self.center, self.sr_recip);
+            let (dl, dr) = self.drum.process(low, speed, self.center, self.sr_recip);
+            left[i] = hl + dl;
+            right[i] = hr + dr;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use modules::testutil::render;
+    use super::Leslie;
+
+    #[test]
+    fn speed_ramps() {
+        let sr = 44_100.0;
+        let mut leslie = Leslie::new(sr);
+        let x = vec![0.0; 4410];
+        render(&mut leslie, &[1.0], &[&x], x.len());
+        // After 0.1s, both rotors are on the way, the horn further along.
+        let horn = (leslie.horn.rate - 0.8) / (6.8 - 0.8);
+        let drum = (leslie.drum.rate - 0.7) / (5.9 - 0.7);
+        assert!(horn > 0.05 && horn < 0.3, "horn {}", horn);
+        assert!(drum > 0.0 && drum < horn, "drum {}", drum);
+        let x = vec![0.0; 441_000];
+        render(&mut leslie, &[1.0], &[&x], x.len());
+        assert!((leslie.horn.rate - 6.8).abs() < 0.01);
+        assert!(leslie.drum.rate > 5.5 && leslie.drum.rate < 5.9);
+        // and back down again
+        let x = vec![0.0; 4410];
+        render(&mut leslie, &[0.0], &[&x], x.len());
+        assert!(leslie.horn.rate > 5.0);
+    }
+}
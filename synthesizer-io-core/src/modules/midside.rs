@@ -0,0 +1,103 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! Mid/side encoding and decoding, so that mid and side can be processed
+//! separately in a graph.
+//!
+//! `MsEncode` takes left and right buffers and outputs mid = (L + R) / 2 and
+//! side = (L - R) / 2. `MsDecode` inverts it, taking mid and side and
+//! outputting L = M + S and R = M - S.
+
+use module::{Module, Buffer};
+
+pub struct MsEncode;
+
+pub struct MsDecode;
+
+impl MsEncode {
+    pub fn new() -> MsEncode {
+        MsEncode
+    }
+}
+
+impl MsDecode {
+    pub fn new() -> MsDecode {
+        MsDecode
+    }
+}
+
+// Apply the butterfly (a, b) -> (scale * (a + b), scale * (a - b)) in place.
+fn butterfly(buf: &mut [Buffer], scale: f32) {
+    let (a, b) = buf.split_at_mut(1);
+    let a = a[0].get_mut();
+    let b = b[0].get_mut();
+    for i in 0..a.len() {
+        let (x, y) = (a[i], b[i]);
+        a[i] = scale * (x + y);
+        b[i] = scale * (x - y);
+    }
+}
+
+impl Module for MsEncode {
+    fn n_bufs_out(&self) -> usize { 2 }
+
+    fn process(&mut self, _control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        buf_out[0].get_mut().copy_from_slice(buf_in[0].get());
+        buf_out[1].get_mut().copy_from_slice(buf_in[1].get());
+        butterfly(buf_out, 0.5);
+    }
+
+    fn process_in_place(&mut self, _control_in: &[f32], buf: &mut [Buffer]) {
+        butterfly(buf, 0.5);
+    }
+}
+
+impl Module for MsDecode {
+    fn n_bufs_out(&self) -> usize { 2 }
+
+    fn process(&mut self, _control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        buf_out[0].get_mut().copy_from_slice(buf_in[0].get());
+        buf_out[1].get_mut().copy_from_slice(buf_in[1].get());
+        butterfly(buf_out, 1.0);
+    }
+
+    fn process_in_place(&mut self, _control_in: &[f32], buf: &mut [Buffer]) {
+        butterfly(buf, 1.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use modules::testutil::{render, sine};
+    use super::{MsEncode, MsDecode};
+
+    #[test]
+    fn round_trip() {
+        let l = sine(0.01, 0.8, 256);
+        let r = sine(0.037, 0.5, 256);
+        let ms = render(&mut MsEncode::new(), &[], &[&l, &r], 256);
+        let lr = render(&mut MsDecode::new(), &[], &[&ms[0], &ms[1]], 256);
+        for i in 0..256 {
+            assert!((ms[0][i] - 0.5 * (l[i] + r[i])).abs() < 1e-6);
+            assert!((ms[1][i] - 0.5 * (l[i] - r[i])).abs() < 1e-6);
+            assert!((lr[0][i] - l[i]).abs() < 1e-6);
+            assert!((lr[1][i] - r[i]).abs() < 1e-6);
+        }
+    }
+}
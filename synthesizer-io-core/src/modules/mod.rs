@@ -25,15 +25,83 @@ mod note_pitch;
 mod adsr;
 mod gain;
 mod monitor;
+mod follower;
+mod rng;
+mod ducker;
+mod tilt;
+mod rotate;
+mod oversample;
+mod wavefold;
+mod edge_detect;
+mod multiphase;
+mod autopan;
+mod filters;
+mod exciter;
+mod midside;
+mod delay;
+mod tzflanger;
+mod vocoder;
+mod tube;
+mod psola;
+mod clockdiv;
+mod drum;
+mod leslie;
+mod operator;
+mod shimmer;
+mod pad;
+mod autowah;
+mod stereodelay;
+mod additive;
+mod resonator;
+mod softclip;
+mod stereoenhance;
+mod agc;
+mod square;
+mod envelope;
+mod noise;
+mod sample_hold;
+
+#[cfg(test)]
+mod testutil;
 
 pub use self::sum::Sum;
 pub use self::buzz::Buzz;
 pub use self::sin::Sin;
 pub use self::saw::Saw;
-pub use self::biquad::Biquad;
+pub use self::biquad::{Biquad, FilterMode};
 pub use self::const_ctrl::ConstCtrl;
 pub use self::smooth_ctrl::SmoothCtrl;
-pub use self::note_pitch::NotePitch;
+pub use self::note_pitch::{NotePitch, note_to_log_freq};
 pub use self::adsr::Adsr;
 pub use self::gain::Gain;
 pub use self::monitor::Monitor;
+pub use self::ducker::Ducker;
+pub use self::tilt::Tilt;
+pub use self::rotate::Rotate;
+pub use self::wavefold::WaveFold;
+pub use self::edge_detect::{Edge, EdgeDetect, EdgeDetector};
+pub use self::multiphase::MultiPhase;
+pub use self::autopan::AutoPan;
+pub use self::exciter::Exciter;
+pub use self::midside::{MsEncode, MsDecode};
+pub use self::tzflanger::TzFlanger;
+pub use self::vocoder::Vocoder;
+pub use self::tube::Tube;
+pub use self::psola::Psola;
+pub use self::clockdiv::ClockDiv;
+pub use self::drum::Drum;
+pub use self::leslie::Leslie;
+pub use self::operator::Operator;
+pub use self::shimmer::Shimmer;
+pub use self::pad::Pad;
+pub use self::autowah::{AutoWah, WahDirection};
+pub use self::stereodelay::StereoDelay;
+pub use self::additive::Additive;
+pub use self::resonator::Resonator;
+pub use self::softclip::SoftClip;
+pub use self::stereoenhance::StereoEnhance;
+pub use self::agc::Agc;
+pub use self::square::Square;
+pub use self::envelope::Envelope;
+pub use self::noise::Noise;
+pub use self::sample_hold::SampleHold;
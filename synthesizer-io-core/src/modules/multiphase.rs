@@ -0,0 +1,85 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! A sine oscillator with multiple outputs at evenly spaced phase offsets,
+//! as a building block for unison and phaser patches.
+//!
+//! The control inputs are frequency (log2 of Hz, as for `Sin`) and phase
+//! spread. Output `k` of `n` is offset by `k * spread / n` cycles, so a
+//! spread of 1 spaces the outputs evenly over a whole cycle, and 0 makes
+//! them identical.
+
+use std::f32::consts;
+
+use module::{Module, Buffer, N_SAMPLES_PER_CHUNK};
+
+pub struct MultiPhase {
+    sr_offset: f32,
+    n: usize,
+    phase: f32,  // in cycles, 0 to 1
+}
+
+impl MultiPhase {
+    pub fn new(sample_rate: f32, n: usize) -> MultiPhase {
+        MultiPhase {
+            sr_offset: -sample_rate.log2(),
+            n,
+            phase: 0.0,
+        }
+    }
+}
+
+impl Module for MultiPhase {
+    fn n_bufs_out(&self) -> usize { self.n }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        _buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        let freq = (control_in[0] + self.sr_offset).exp2();
+        let spread = control_in[1] / self.n as f32;
+        for (k, buf) in buf_out.iter_mut().enumerate() {
+            let mut phase = self.phase + k as f32 * spread;
+            for y in buf.get_mut().iter_mut() {
+                *y = (2.0 * consts::PI * phase).sin();
+                phase += freq;
+            }
+        }
+        let phase = self.phase + freq * N_SAMPLES_PER_CHUNK as f32;
+        self.phase = phase - phase.floor();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use modules::testutil::{render, amplitude};
+    use super::MultiPhase;
+
+    #[test]
+    fn evenly_offset() {
+        let sr = 44_100.0;
+        let period = 128;
+        let freq = sr / period as f32;
+        let mut mp = MultiPhase::new(sr, 4);
+        let y = render(&mut mp, &[freq.log2(), 1.0], &[], 1024);
+        assert_eq!(y.len(), 4);
+        for k in 0..4 {
+            assert!((amplitude(&y[k], 1.0 / period as f32) - 1.0).abs() < 1e-3);
+            for i in 0..512 {
+                // output k leads output 0 by k quarter cycles
+                assert!((y[k][i] - y[0][i + k * period / 4]).abs() < 1e-3);
+            }
+        }
+    }
+}
@@ -0,0 +1,65 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! A source of uniform white noise, from a xorshift generator.
+//!
+//! The seed is given when it's created, so the output is reproducible.
+
+use module::{Module, Buffer};
+use super::rng::Rng;
+
+pub struct Noise {
+    rng: Rng,
+}
+
+impl Noise {
+    pub fn new(seed: u32) -> Noise {
+        Noise { rng: Rng::new(seed) }
+    }
+}
+
+impl Module for Noise {
+    fn n_bufs_out(&self) -> usize { 1 }
+
+    fn process(&mut self, _control_in: &[f32], _control_out: &mut [f32],
+        _buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        for y in buf_out[0].get_mut().iter_mut() {
+            *y = self.rng.bipolar();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use modules::testutil::render;
+    use super::Noise;
+
+    #[test]
+    fn reproducible() {
+        let a = render(&mut Noise::new(42), &[], &[], 32).remove(0);
+        let b = render(&mut Noise::new(42), &[], &[], 32).remove(0);
+        let c = render(&mut Noise::new(43), &[], &[], 32).remove(0);
+        assert_eq!(a, b);
+        assert!(a != c);
+        let y = render(&mut Noise::new(0), &[], &[], 32768).remove(0);
+        assert!(y.iter().all(|&x| x >= -1.0 && x < 1.0));
+        let mean = y.iter().sum::<f32>() / y.len() as f32;
+        let power = y.iter().map(|x| x * x).sum::<f32>() / y.len() as f32;
+        assert!(mean.abs() < 0.02, "mean {}", mean);
+        // uniform on [-1, 1] has power 1/3
+        assert!((power - 1.0 / 3.0).abs() < 0.01, "power {}", power);
+    }
+}
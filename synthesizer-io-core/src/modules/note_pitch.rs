@@ -16,6 +16,15 @@
 
 use module::{Module, Buffer};
 
+/// The frequency of A4 (440Hz), as log2 of Hz. Oscillators use it until
+/// a frequency control input arrives.
+pub const A4_LOG_FREQ: f32 = 8.78135971352466;
+
+/// Convert a MIDI note number to a frequency, as log2 of Hz (A4, note 69, is 440Hz).
+pub fn note_to_log_freq(midi_num: f32) -> f32 {
+    midi_num * (1.0 / 12.0) + (440f32.log2() - 69.0 / 12.0)
+}
+
 pub struct NotePitch {
     value: f32,
 }
@@ -31,7 +40,7 @@ impl Module for NotePitch {
 
     fn handle_note(&mut self, midi_num: f32, _velocity: f32, on: bool) {
         if on {
-            self.value = midi_num * (1.0 / 12.0) + (440f32.log2() - 69.0 / 12.0);
+            self.value = note_to_log_freq(midi_num);
         }
     }
 
@@ -0,0 +1,118 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! An FM (more precisely, phase modulation) operator: a sine oscillator with
+//! a phase modulation input and self-feedback, as in DX-style synthesizers.
+//! Operators are connected into algorithms by feeding the output of one into
+//! the modulation input of another.
+//!
+//! The buffer input, if connected, is the phase modulation in radians.
+//! Control inputs are the base frequency (log2 Hz, e.g. from `NotePitch`),
+//! the frequency ratio, the feedback amount (0 to 1), and the output level
+//! (log2 of gain, e.g. from `Adsr`, for an amplitude envelope). The level is
+//! linearly smoothed across each chunk.
+
+use std::f32::consts;
+
+use module::{Module, Buffer};
+
+pub struct Operator {
+    sr_offset: f32,
+    phase: f32,  // in cycles, 0 to 1
+    last_g: f32,
+    // the last two outputs (before level), for feedback
+    y1: f32,
+    y2: f32,
+}
+
+impl Operator {
+    pub fn new(sample_rate: f32) -> Operator {
+        Operator {
+            sr_offset: -sample_rate.log2(),
+            phase: 0.0,
+            last_g: 0.0,
+            y1: 0.0,
+            y2: 0.0,
+        }
+    }
+}
+
+impl Module for Operator {
+    fn n_bufs_out(&self) -> usize { 1 }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        let freq = (control_in[0] + self.sr_offset).exp2() * control_in[1];
+        // Averaging the last two outputs suppresses the parasitic oscillation
+        // that single-sample feedback develops at high amounts.
+        let beta = 0.5 * consts::PI * control_in[2].max(0.0).min(1.0);
+        let g = control_in[3].exp2();
+        let out = buf_out[0].get_mut();
+        let dg = (g - self.last_g) * (1.0 / out.len() as f32);
+        let mut level = self.last_g + dg;
+        self.last_g = g;
+        for i in 0..out.len() {
+            let pm = buf_in.first().map(|b| b.get()[i]).unwrap_or(0.0);
+            let th = 2.0 * consts::PI * self.phase + pm + beta * (self.y1 + self.y2);
+            let y = th.sin();
+            self.y2 = self.y1;
+            self.y1 = y;
+            out[i] = level * y;
+            level += dg;
+            self.phase += freq;
+            self.phase -= self.phase.floor();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use modules::testutil::{render, sine, amplitude};
+    use super::Operator;
+
+    const SR: f32 = 44_100.0;
+
+    #[test]
+    fn feedback_adds_harmonics() {
+        let f: f32 = 441.0;
+        let harmonics = |feedback: f32| {
+            let ctrl = [f.log2(), 1.0, feedback, 0.0];
+            let y = render(&mut Operator::new(SR), &ctrl, &[], 4096).remove(0);
+            let y = &y[100..4000];
+            (amplitude(y, f / SR), amplitude(y, 2.0 * f / SR), amplitude(y, 3.0 * f / SR))
+        };
+        let (h1, h2, h3) = harmonics(0.0);
+        assert!((h1 - 1.0).abs() < 0.01 && h2 < 0.001 && h3 < 0.001);
+        let (_, h2, h3) = harmonics(0.7);
+        assert!(h2 > 0.1 && h3 > 0.05, "{} {}", h2, h3);
+    }
+
+    #[test]
+    fn pm_sidebands() {
+        // carrier 2205Hz modulated at 441Hz with an index of 1 radian
+        let fc = 2205.0;
+        let fm = 441.0 / SR;
+        let modulator = sine(fm, 1.0, 4096);
+        let ctrl = [(fc / 2.0f32).log2(), 2.0, 0.0, 0.0];
+        let y = render(&mut Operator::new(SR), &ctrl, &[&modulator], 4096).remove(0);
+        let y = &y[100..4000];
+        // sidebands follow the Bessel functions: J0(1) = 0.765, J1(1) = 0.440
+        let fc = fc / SR;
+        assert!((amplitude(y, fc) - 0.765).abs() < 0.02);
+        assert!((amplitude(y, fc - fm) - 0.440).abs() < 0.02);
+        assert!((amplitude(y, fc + fm) - 0.440).abs() < 0.02);
+    }
+}
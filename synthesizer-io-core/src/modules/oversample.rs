@@ -0,0 +1,84 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! Oversampling for nonlinear modules, to reduce aliasing.
+
+use std::f32::consts;
+
+/// The oversampling factor.
+pub const FACTOR: usize = 4;
+
+// Length of the (linear phase) FIR lowpass, at the oversampled rate.
+const N_TAPS: usize = 32;
+const N_PHASE_TAPS: usize = N_TAPS / FACTOR;
+
+/// A 4x oversampler. Each input sample is interpolated up to `FACTOR`
+/// samples, passed through a nonlinearity, then filtered and decimated
+/// back down. Latency is about `N_TAPS / FACTOR` input samples.
+pub struct Oversampler {
+    coefs: [f32; N_TAPS],
+    up_hist: [f32; N_PHASE_TAPS],
+    down_hist: [f32; N_TAPS],
+}
+
+impl Oversampler {
+    pub fn new() -> Oversampler {
+        // Blackman-windowed sinc with cutoff slightly below the original Nyquist.
+        let mut coefs = [0.0; N_TAPS];
+        let fc = 0.45 / FACTOR as f32;
+        let center = (N_TAPS - 1) as f32 * 0.5;
+        let mut sum = 0.0;
+        for k in 0..N_TAPS {
+            let t = k as f32 - center;
+            let sinc = (2.0 * consts::PI * fc * t).sin() / (consts::PI * t);
+            let w = 2.0 * consts::PI * k as f32 / (N_TAPS - 1) as f32;
+            let window = 0.42 - 0.5 * w.cos() + 0.08 * (2.0 * w).cos();
+            coefs[k] = sinc * window;
+            sum += coefs[k];
+        }
+        for c in &mut coefs {
+            *c /= sum;
+        }
+        Oversampler {
+            coefs,
+            up_hist: [0.0; N_PHASE_TAPS],
+            down_hist: [0.0; N_TAPS],
+        }
+    }
+
+    /// Process one sample through the nonlinearity `f` at the oversampled rate.
+    pub fn process<F: FnMut(f32) -> f32>(&mut self, x: f32, mut f: F) -> f32 {
+        for j in (1..N_PHASE_TAPS).rev() {
+            self.up_hist[j] = self.up_hist[j - 1];
+        }
+        self.up_hist[0] = x;
+        for p in 0..FACTOR {
+            // polyphase interpolation; the zero-stuffing loses a factor of FACTOR
+            let mut u = 0.0;
+            for j in 0..N_PHASE_TAPS {
+                u += self.coefs[p + FACTOR * j] * self.up_hist[j];
+            }
+            for k in (1..N_TAPS).rev() {
+                self.down_hist[k] = self.down_hist[k - 1];
+            }
+            self.down_hist[0] = f(u * FACTOR as f32);
+        }
+        let mut y = 0.0;
+        for k in 0..N_TAPS {
+            y += self.coefs[k] * self.down_hist[k];
+        }
+        y
+    }
+}
@@ -0,0 +1,161 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! A granular freeze: while the freeze control is high, the input captured
+//! just before freezing is sustained indefinitely as a pad, by overlapping
+//! grains read from random positions in the capture, at randomly detuned
+//! pitches.
+//!
+//! Control inputs are the freeze (frozen above 0.5) and the pitch spread of
+//! the grains, in semitones either side of the original pitch. The output is
+//! silent when not frozen, apart from grains finishing after a release.
+
+use std::f32::consts;
+
+use module::{Module, Buffer};
+use super::rng::Rng;
+
+// Length of the capture, in seconds.
+const CAPTURE: f32 = 0.5;
+
+// Length of each grain, in seconds.
+const GRAIN: f32 = 0.1;
+
+// Number of grains overlapping at any time.
+const OVERLAP: usize = 4;
+
+#[derive(Clone, Copy)]
+struct Grain {
+    pos: f32,  // read position, in samples from the start of the capture
+    rate: f32,
+    age: usize,  // in samples; the grain is finished at grain_len
+}
+
+pub struct Pad {
+    capture: Vec<f32>,
+    write_pos: usize,
+    frozen: bool,
+    grain_len: usize,
+    // samples until the next grain starts
+    countdown: usize,
+    grains: [Option<Grain>; OVERLAP + 1],
+    rng: Rng,
+}
+
+impl Pad {
+    pub fn new(sample_rate: f32) -> Pad {
+        Pad {
+            capture: vec![0.0; (CAPTURE * sample_rate) as usize],
+            write_pos: 0,
+            frozen: false,
+            grain_len: (GRAIN * sample_rate) as usize,
+            countdown: 0,
+            grains: [None; OVERLAP + 1],
+            rng: Rng::new(1),
+        }
+    }
+
+    fn start_grain(&mut self, spread: f32) {
+        let rate = (self.rng.bipolar() * spread * (1.0 / 12.0)).exp2();
+        // leave room for the grain to play out at its rate
+        let room = self.capture.len() as f32 - (self.grain_len as f32 + 1.0) * rate;
+        let pos = self.rng.unit() * room.max(0.0);
+        if let Some(slot) = self.grains.iter_mut().find(|g| g.is_none()) {
+            *slot = Some(Grain { pos, rate, age: 0 });
+        }
+    }
+
+    // Read the frozen capture, where position 0 is the oldest sample.
+    fn read(&self, pos: f32) -> f32 {
+        let n = self.capture.len();
+        let i = pos as usize;
+        let frac = pos - i as f32;
+        let x0 = self.capture[(self.write_pos + i) % n];
+        let x1 = self.capture[(self.write_pos + i + 1) % n];
+        x0 + frac * (x1 - x0)
+    }
+
+    fn process_sample(&mut self, x: f32, spread: f32) -> f32 {
+        if !self.frozen {
+            self.capture[self.write_pos] = x;
+            self.write_pos = (self.write_pos + 1) % self.capture.len();
+        } else {
+            if self.countdown == 0 {
+                self.start_grain(spread);
+                self.countdown = self.grain_len / OVERLAP;
+            }
+            self.countdown -= 1;
+        }
+        let d = consts::PI / self.grain_len as f32;
+        let mut y = 0.0;
+        for k in 0..self.grains.len() {
+            if let Some(mut g) = self.grains[k] {
+                let w = (d * g.age as f32).sin().powi(2);
+                y += w * self.read(g.pos);
+                g.pos += g.rate;
+                g.age += 1;
+                self.grains[k] = if g.age < self.grain_len { Some(g) } else { None };
+            }
+        }
+        // The Hann windows of the overlapping grains sum to OVERLAP / 2.
+        y * (2.0 / OVERLAP as f32)
+    }
+}
+
+impl Module for Pad {
+    fn n_bufs_out(&self) -> usize { 1 }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        let freeze = control_in[0] > 0.5;
+        if freeze && !self.frozen {
+            self.countdown = 0;
+        }
+        self.frozen = freeze;
+        let spread = control_in[1].max(0.0);
+        let inb = buf_in[0].get();
+        let out = buf_out[0].get_mut();
+        for i in 0..out.len() {
+            out[i] = self.process_sample(inb[i], spread);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use modules::testutil::{render, sine, amplitude};
+    use super::Pad;
+
+    #[test]
+    fn sustains_after_freeze() {
+        let sr = 44_100.0;
+        let f = 441.0 / sr;
+        let mut pad = Pad::new(sr);
+        let x = sine(f, 0.5, 44_096);
+        let y = render(&mut pad, &[0.0, 0.1], &[&x], x.len()).remove(0);
+        assert!(y.iter().all(|&y| y == 0.0));
+        // freeze, then the input goes silent for 5 seconds
+        let silence = vec![0.0; 5 * 44_096];
+        let y = render(&mut pad, &[1.0, 0.1], &[&silence], silence.len()).remove(0);
+        let tail = &y[y.len() - 22_050..];
+        let rms = (tail.iter().map(|y| y * y).sum::<f32>() / tail.len() as f32).sqrt();
+        assert!(rms > 0.1, "{}", rms);
+        // The grains aren't phase coherent, so measure over short windows.
+        let level = |f: f32| tail.chunks(2205).map(|w| amplitude(w, f)).sum::<f32>();
+        let a = level(f);
+        assert!(level(2.0 * f) < 0.1 * a && level(0.5 * f) < 0.1 * a);
+    }
+}
@@ -0,0 +1,234 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! A pitch shifter using time-domain pitch-synchronous overlap-add (PSOLA).
+//!
+//! The period of the (monophonic) input is tracked by autocorrelation, and
+//! pitch marks are placed on the signal peaks one period apart. Each output
+//! grain is a Hann-windowed span of two periods centered on the analysis mark
+//! nearest in time; grains are laid down at the period divided by the shift
+//! ratio. Because each grain keeps its own waveform, the spectral envelope
+//! (formants) and duration are preserved.
+//!
+//! The control input is the shift in octaves (log2 of the ratio). The output
+//! is delayed by four times the longest tracked period.
+
+use std::collections::VecDeque;
+use std::f32::consts;
+
+use module::{Module, Buffer};
+
+// Range of tracked fundamental frequencies, in Hz.
+const MIN_FREQ: f32 = 60.0;
+const MAX_FREQ: f32 = 1000.0;
+
+// Interval between period estimates, in samples.
+const HOP: usize = 256;
+
+// Minimum normalized autocorrelation to count as voiced.
+const VOICED_THRESH: f32 = 0.5;
+
+pub struct Psola {
+    min_period: usize,
+    max_period: usize,
+    latency: usize,
+    period: usize,
+    hist: Vec<f32>,
+    acc: Vec<f32>,
+    wsum: Vec<f32>,
+    // Normalized autocorrelation for each lag from min to max period.
+    corr: Vec<f32>,
+    // Total number of samples written.
+    n: usize,
+    next_mark: usize,
+    next_synth: f32,
+    // Analysis marks, as (position, period).
+    marks: VecDeque<(usize, usize)>,
+}
+
+impl Psola {
+    pub fn new(sample_rate: f32) -> Psola {
+        let min_period = (sample_rate / MAX_FREQ) as usize;
+        let max_period = (sample_rate / MIN_FREQ).ceil() as usize;
+        let latency = 4 * max_period;
+        Psola {
+            min_period,
+            max_period,
+            latency,
+            period: (min_period + max_period) / 2,
+            hist: vec![0.0; latency + 4 * max_period],
+            acc: vec![0.0; 4 * max_period],
+            wsum: vec![0.0; 4 * max_period],
+            corr: vec![0.0; max_period - min_period + 1],
+            n: 0,
+            next_mark: 2 * max_period,
+            next_synth: (2 * max_period) as f32,
+            // Marks are kept from two max periods before the output time up
+            // to the input, at least 3/4 of a min period apart, so this is
+            // enough to avoid allocating later.
+            marks: VecDeque::with_capacity((latency + 2 * max_period) * 4 / (3 * min_period) + 2),
+        }
+    }
+
+    fn hist(&self, i: usize) -> f32 {
+        self.hist[i % self.hist.len()]
+    }
+
+    // Estimate the period from the most recent two max periods of input.
+    // The first autocorrelation peak close to the best one is chosen, to
+    // avoid locking onto a multiple of the period.
+    fn update_period(&mut self) {
+        let w = 2 * self.max_period;
+        let start = self.n - w;
+        for lag in self.min_period..self.max_period + 1 {
+            let (mut xy, mut xx, mut yy) = (0.0, 0.0, 0.0);
+            for i in start + lag..self.n {
+                let x = self.hist(i);
+                let y = self.hist(i - lag);
+                xy += x * y;
+                xx += x * x;
+                yy += y * y;
+            }
+            self.corr[lag - self.min_period] = xy / (xx * yy + 1e-12).sqrt();
+        }
+        let corr = &self.corr;
+        let best = corr.iter().cloned().fold(0.0, f32::max);
+        if best < VOICED_THRESH {
+            return;
+        }
+        for i in 1..corr.len() - 1 {
+            if corr[i] >= 0.9 * best && corr[i] >= corr[i - 1] && corr[i] >= corr[i + 1] {
+                self.period = self.min_period + i;
+                return;
+            }
+        }
+    }
+
+    // Place the next analysis mark at the peak within a quarter period of
+    // where it is expected.
+    fn place_mark(&mut self) {
+        let p = self.period;
+        let mut mark = self.next_mark;
+        let mut peak = self.hist(mark);
+        for i in self.next_mark - p / 4..self.next_mark + p / 4 + 1 {
+            if self.hist(i) > peak {
+                peak = self.hist(i);
+                mark = i;
+            }
+        }
+        self.marks.push_back((mark, p));
+        self.next_mark = mark + p;
+    }
+
+    // Overlap-add a grain centered at output time `s`, returning its period.
+    fn place_grain(&mut self, s: usize) -> usize {
+        let &(a, p) = self.marks.iter()
+            .min_by_key(|&&(a, _)| if a > s { a - s } else { s - a })
+            .unwrap();
+        let len = self.acc.len();
+        let d = consts::PI / p as f32;
+        for j in 1..2 * p {
+            let w = 0.5 - 0.5 * (d * j as f32).cos();
+            let dst = (s - p + j) % len;
+            self.acc[dst] += w * self.hist(a - p + j);
+            self.wsum[dst] += w;
+        }
+        p
+    }
+
+    fn process_sample(&mut self, x: f32, ratio: f32) -> f32 {
+        let len = self.hist.len();
+        self.hist[self.n % len] = x;
+        self.n += 1;
+        if self.n % HOP == 0 && self.n >= 2 * self.max_period {
+            self.update_period();
+        }
+        while self.n > self.next_mark + self.period / 4 {
+            self.place_mark();
+        }
+        if self.n < self.latency {
+            return 0.0;
+        }
+        let t = self.n - self.latency;
+        while let Some(&(a, _)) = self.marks.front() {
+            if a + 2 * self.max_period >= t {
+                break;
+            }
+            self.marks.pop_front();
+        }
+        while self.next_synth as usize <= t + self.max_period {
+            let s = self.next_synth as usize;
+            let p = self.place_grain(s);
+            self.next_synth += p as f32 / ratio;
+        }
+        let ix = t % self.acc.len();
+        let y = self.acc[ix] / self.wsum[ix].max(1.0);
+        self.acc[ix] = 0.0;
+        self.wsum[ix] = 0.0;
+        y
+    }
+}
+
+impl Module for Psola {
+    fn n_bufs_out(&self) -> usize { 1 }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        let ratio = control_in[0].exp2();
+        let inb = buf_in[0].get();
+        let out = buf_out[0].get_mut();
+        for i in 0..out.len() {
+            out[i] = self.process_sample(inb[i], ratio);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts;
+
+    use modules::testutil::{render, amplitude};
+    use super::Psola;
+
+    // A harmonic series with a spectral envelope peaking at 900Hz.
+    fn vowel(f0: f32, sr: f32, n: usize) -> Vec<f32> {
+        let formant = |f: f32| 0.1 + (-((f - 900.0) / 300.0).powi(2)).exp();
+        (0..n).map(|i| {
+            (1..20).map(|k| {
+                let f = f0 * k as f32;
+                0.1 * formant(f) * (2.0 * consts::PI * f * i as f32 / sr).sin()
+            }).sum()
+        }).collect()
+    }
+
+    #[test]
+    fn octave_up_keeps_formant() {
+        let sr = 44_100.0;
+        let f0 = 147.0;
+        let n = 32768;
+        let x = vowel(f0, sr, n);
+        let y = render(&mut Psola::new(sr), &[1.0], &[&x], n).remove(0);
+        let y = &y[n / 2..];
+        let harmonic = |f: f32| amplitude(y, f / sr);
+        // The old fundamental is gone and the new one is present.
+        assert!(harmonic(f0) < 0.2 * harmonic(2.0 * f0),
+            "{} {}", harmonic(f0), harmonic(2.0 * f0));
+        // The strongest harmonic is still the one nearest the formant.
+        let peak = (1..8).max_by(|&a, &b| harmonic(2.0 * f0 * a as f32)
+            .partial_cmp(&harmonic(2.0 * f0 * b as f32)).unwrap()).unwrap();
+        assert_eq!(peak, 3);
+    }
+}
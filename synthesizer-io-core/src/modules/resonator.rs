@@ -0,0 +1,81 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! A resonator: a feedback comb filter tuned to a MIDI note, which rings at
+//! that pitch when excited by an impulse or noise (the buffer input).
+//!
+//! Control inputs are the MIDI note number and the decay time (the time to
+//! fall by 60dB, in seconds).
+
+use module::{Module, Buffer};
+use super::delay::DelayLine;
+use super::note_pitch::note_to_log_freq;
+
+// The lowest note that fits in the delay line.
+const MIN_FREQ: f32 = 20.0;
+
+pub struct Resonator {
+    sample_rate: f32,
+    delay: DelayLine,
+}
+
+impl Resonator {
+    pub fn new(sample_rate: f32) -> Resonator {
+        Resonator {
+            sample_rate,
+            delay: DelayLine::new((sample_rate / MIN_FREQ).ceil() as usize),
+        }
+    }
+}
+
+impl Module for Resonator {
+    fn n_bufs_out(&self) -> usize { 1 }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        let freq = note_to_log_freq(control_in[0]).exp2().max(MIN_FREQ);
+        let period = self.sample_rate / freq;
+        let decay = control_in[1].max(1e-3);
+        // -60dB after `decay` seconds
+        let g = (-3.0 * 10f32.log2() * period / (decay * self.sample_rate)).exp2();
+        let inb = buf_in[0].get();
+        let out = buf_out[0].get_mut();
+        for i in 0..out.len() {
+            // the read comes before the write, hence one less than the period
+            let y = inb[i] + g * self.delay.read(period - 1.0);
+            self.delay.write(y);
+            out[i] = y;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use modules::testutil::{render, amplitude};
+    use super::Resonator;
+
+    #[test]
+    fn rings_at_note() {
+        let sr = 44_100.0;
+        let mut x = vec![0.0; 8192];
+        x[0] = 1.0;
+        let y = render(&mut Resonator::new(sr), &[69.0, 2.0], &[&x], x.len()).remove(0);
+        let a = |hz: f32| amplitude(&y, hz / sr);
+        // A4, not the neighboring semitones
+        assert!(a(440.0) > 0.01, "{}", a(440.0));
+        assert!(a(415.3) < 0.2 * a(440.0) && a(466.2) < 0.2 * a(440.0));
+    }
+}
@@ -0,0 +1,82 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! Sample and hold: the input is sampled at a regular rate, and held in
+//! between.
+//!
+//! The control input is the sampling rate (log2 Hz, like oscillator
+//! frequencies).
+
+use module::{Module, Buffer};
+
+pub struct SampleHold {
+    sr_offset: f32,
+    // fraction of the sampling period elapsed
+    phase: f32,
+    value: f32,
+}
+
+impl SampleHold {
+    pub fn new(sample_rate: f32) -> SampleHold {
+        SampleHold {
+            sr_offset: -sample_rate.log2(),
+            // take the first sample immediately
+            phase: 1.0,
+            value: 0.0,
+        }
+    }
+}
+
+impl Module for SampleHold {
+    fn n_bufs_out(&self) -> usize { 1 }
+
+    fn n_ctrl_in(&self) -> usize { 1 }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        let step = (control_in[0] + self.sr_offset).exp2();
+        let inb = buf_in[0].get();
+        let out = buf_out[0].get_mut();
+        for i in 0..out.len() {
+            if self.phase >= 1.0 {
+                self.phase -= self.phase.floor();
+                self.value = inb[i];
+            }
+            out[i] = self.value;
+            self.phase += step;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use modules::testutil::render;
+    use super::SampleHold;
+
+    #[test]
+    fn holds() {
+        let x: Vec<f32> = (0..256).map(|i| i as f32).collect();
+        // 1kHz at 32kHz: a new value every 32 samples
+        let mut sh = SampleHold::new(32_000.0);
+        let y = render(&mut sh, &[1000f32.log2()], &[&x], 256).remove(0);
+        for (i, &y) in y.iter().enumerate() {
+            assert_eq!(y, (i / 32 * 32) as f32);
+        }
+        // a rate above the sample rate passes the input through
+        let y = render(&mut sh, &[40_000f32.log2()], &[&x], 256).remove(0);
+        assert_eq!(y, x);
+    }
+}
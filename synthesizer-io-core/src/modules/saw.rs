@@ -19,6 +19,7 @@ use std::ops::Deref;
 use std::cmp::min;
 
 use module::{Module, Buffer};
+use super::note_pitch::A4_LOG_FREQ;
 
 const LG_N_SAMPLES: usize = 10;
 const N_SAMPLES: usize = (1 << LG_N_SAMPLES);
@@ -76,6 +77,8 @@ lazy_static! {
 pub struct Saw {
     sr_offset: f32,
     phase: f32,
+    // last frequency from the control input, as log2 Hz
+    freq: f32,
 }
 
 impl Saw {
@@ -85,6 +88,7 @@ impl Saw {
         Saw {
             sr_offset: LG_N_SAMPLES as f32 - sample_rate.log2(),
             phase: 0.0,
+            freq: A4_LOG_FREQ,
         }
     }
 }
@@ -96,10 +100,23 @@ fn compute(tab_ix: usize, phasefrac: f32) -> f32 {
 impl Module for Saw {
     fn n_bufs_out(&self) -> usize { 1 }
 
+    fn n_ctrl_in(&self) -> usize { 1 }
+
+    fn migrate(&mut self, old: &mut dyn Module) {
+        if let Some(old_saw) = old.to_any().downcast_ref::<Saw>() {
+            self.phase = old_saw.phase;
+            self.freq = old_saw.freq;
+        }
+    }
+
     fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
         _buf_in: &[&Buffer], buf_out: &mut [Buffer])
     {
-        let logf = control_in[0] + self.sr_offset;
+        // As with Sin, an unconnected frequency holds the last one.
+        if let Some(&freq) = control_in.first() {
+            self.freq = freq;
+        }
+        let logf = self.freq + self.sr_offset;
         let slice_off = -SLICE_BASE - LG_N_SAMPLES as f32;
         let slice = (logf + slice_off) * SLICES_PER_OCTAVE as f32;
         //println!("logf={}, slice={}", logf, slice);
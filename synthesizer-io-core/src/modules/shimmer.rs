@@ -0,0 +1,169 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! A shimmer reverb: a feedback delay network reverb with a pitch shifter in
+//! a regeneration path, so each pass round the loop is shifted up (by an
+//! octave or a fifth, say) and the tail climbs in pitch over time.
+//!
+//! The input is mono and the output stereo. Control inputs are the pitch
+//! interval in semitones and the feedback amount (0 to 1) of the shifted
+//! signal back into the reverb.
+
+use module::{Module, Buffer};
+use super::delay::DelayLine;
+use super::filters::OnePole;
+
+// Lengths of the delay lines of the network, in seconds. These are mutually
+// prime when expressed in samples at common rates.
+const LENGTHS: [f32; 4] = [0.0297, 0.0371, 0.0411, 0.0437];
+
+// Gain round the network loop, setting the decay time of the plain reverb.
+const DECAY: f32 = 0.8;
+
+// Cutoff of the damping filters in the loop, in Hz.
+const DAMPING: f32 = 6000.0;
+
+// Window of the pitch shifter, in seconds.
+const WINDOW: f32 = 0.05;
+
+/// A delay-line pitch shifter: two taps sweep through the delay at the rate
+/// needed for the shift, each faded in and out so that its jump back (when
+/// it reaches the end of the window) is silent.
+struct PitchShifter {
+    delay: DelayLine,
+    window: f32,  // in samples
+    phase: f32,  // 0 to 1
+}
+
+impl PitchShifter {
+    fn new(window: usize) -> PitchShifter {
+        PitchShifter {
+            delay: DelayLine::new(window),
+            window: window as f32,
+            phase: 0.0,
+        }
+    }
+
+    fn process(&mut self, x: f32, ratio: f32) -> f32 {
+        self.delay.write(x);
+        self.phase += (ratio - 1.0) / self.window;
+        self.phase -= self.phase.floor();
+        let p2 = if self.phase < 0.5 { self.phase + 0.5 } else { self.phase - 0.5 };
+        // The squared sines of the two taps sum to 1.
+        let g1 = (::std::f32::consts::PI * self.phase).sin().powi(2);
+        let y1 = self.delay.read((1.0 - self.phase) * self.window);
+        let y2 = self.delay.read((1.0 - p2) * self.window);
+        g1 * y1 + (1.0 - g1) * y2
+    }
+}
+
+pub struct Shimmer {
+    lengths: [f32; 4],  // in samples
+    lines: Vec<DelayLine>,
+    damp_coef: f32,
+    damp: [OnePole; 4],
+    shifter: PitchShifter,
+    last: f32,
+}
+
+impl Shimmer {
+    pub fn new(sample_rate: f32) -> Shimmer {
+        let mut lengths = [0.0; 4];
+        for (l, t) in lengths.iter_mut().zip(&LENGTHS) {
+            *l = (t * sample_rate).round();
+        }
+        Shimmer {
+            lengths,
+            lines: lengths.iter().map(|&l| DelayLine::new(l as usize)).collect(),
+            damp_coef: OnePole::coef(DAMPING, sample_rate),
+            damp: [OnePole::new(), OnePole::new(), OnePole::new(), OnePole::new()],
+            shifter: PitchShifter::new((WINDOW * sample_rate) as usize),
+            last: 0.0,
+        }
+    }
+}
+
+impl Module for Shimmer {
+    fn n_bufs_out(&self) -> usize { 2 }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        let ratio = (control_in[0] * (1.0 / 12.0)).exp2();
+        let feedback = control_in[1].max(0.0).min(1.0);
+        let inb = buf_in[0].get();
+        let (left, right) = buf_out.split_at_mut(1);
+        let left = left[0].get_mut();
+        let right = right[0].get_mut();
+        for i in 0..left.len() {
+            let shifted = self.shifter.process(self.last, ratio);
+            // soft limit, in case the regeneration runs away
+            let inject = (inb[i] + feedback * shifted).tanh();
+            let mut o = [0.0; 4];
+            for k in 0..4 {
+                let y = self.lines[k].read(self.lengths[k] - 1.0);
+                o[k] = self.damp[k].process(y, self.damp_coef);
+            }
+            // Hadamard mixing (orthogonal, so the loop gain is just DECAY)
+            let h = [
+                o[0] + o[1] + o[2] + o[3],
+                o[0] - o[1] + o[2] - o[3],
+                o[0] + o[1] - o[2] - o[3],
+                o[0] - o[1] - o[2] + o[3],
+            ];
+            for k in 0..4 {
+                self.lines[k].write(inject + 0.5 * DECAY * h[k]);
+            }
+            self.last = 0.25 * (o[0] + o[1] + o[2] + o[3]);
+            left[i] = 0.5 * (o[0] + o[2]);
+            right[i] = 0.5 * (o[1] + o[3]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use modules::testutil::{render, sine, amplitude};
+    use super::Shimmer;
+
+    #[test]
+    fn tail_rises() {
+        let sr = 44_100.0;
+        let f0 = 220.0 / sr;
+        let n = 2 * 44_096;
+        // a sustained tone for half a second, then silence
+        let x: Vec<f32> = sine(f0, 0.2, n).iter().enumerate()
+            .map(|(i, x)| if i < 22_050 { *x } else { 0.0 }).collect();
+        let y = render(&mut Shimmer::new(sr), &[12.0, 0.9], &[&x], n);
+        let mono: Vec<f32> = y[0].iter().zip(&y[1]).map(|(l, r)| l + r).collect();
+        // spectral centroid over the octaves of the input, in 1/4s windows
+        let centroid = |k: usize| {
+            let w = &mono[k * 11_025..(k + 1) * 11_025];
+            let (mut num, mut den) = (0.0, 0.0);
+            for j in 0..5 {
+                let f = f0 * (1 << j) as f32;
+                let a = amplitude(w, f);
+                num += f * a;
+                den += a;
+            }
+            num / den
+        };
+        // while the input is on, the fundamental dominates; in the tail the
+        // octaves above take over
+        let c: Vec<f32> = (1..6).map(|k| centroid(k) / f0).collect();
+        assert!(c[0] < 1.5, "{:?}", c);
+        assert!(c[1..].iter().all(|&x| x > 2.0), "{:?}", c);
+    }
+}
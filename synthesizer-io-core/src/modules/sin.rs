@@ -18,6 +18,7 @@ use std::f32::consts;
 use std::ops::Deref;
 
 use module::{Module, Buffer};
+use super::note_pitch::A4_LOG_FREQ;
 
 const LG_N_SAMPLES: usize = 10;
 const N_SAMPLES: usize = (1 << LG_N_SAMPLES);
@@ -40,6 +41,8 @@ lazy_static! {
 pub struct Sin {
     sr_offset: f32,
     phase: f32,
+    // last frequency from the control input, as log2 Hz
+    freq: f32,
 }
 
 impl Sin {
@@ -49,6 +52,7 @@ impl Sin {
         Sin {
             sr_offset: LG_N_SAMPLES as f32 - sample_rate.log2(),
             phase: 0.0,
+            freq: A4_LOG_FREQ,
         }
     }
 }
@@ -56,19 +60,28 @@ impl Sin {
 impl Module for Sin {
     fn n_bufs_out(&self) -> usize { 1 }
 
+    fn n_ctrl_in(&self) -> usize { 1 }
+
     // Example of migration, although replacing one Sin module with another
     // isn't going to have much use unless the sample rate is changing. But
     // if so, at least the phase will be continuous now.
     fn migrate(&mut self, old: &mut dyn Module) {
         if let Some(old_sin) = old.to_any().downcast_ref::<Sin>() {
             self.phase = old_sin.phase;
+            self.freq = old_sin.freq;
         }
     }
 
     fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
         _buf_in: &[&Buffer], buf_out: &mut [Buffer])
     {
-        let freq = (control_in[0] + self.sr_offset).exp2();
+        // The frequency (log2 Hz) is control input 0. If it isn't connected,
+        // the last frequency seen is held. The phase carries over from the
+        // previous chunk, so frequency changes are click-free.
+        if let Some(&freq) = control_in.first() {
+            self.freq = freq;
+        }
+        let freq = (self.freq + self.sr_offset).exp2();
         let tab = SINTAB.deref();
         let out = buf_out[0].get_mut();
         let mut phase = self.phase;
@@ -84,3 +97,23 @@ impl Module for Sin {
         self.phase = phase - (phaseint & -(N_SAMPLES as i32)) as f32;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use modules::testutil::{render, amplitude};
+    use super::Sin;
+
+    #[test]
+    fn frequency_control() {
+        let sr = 44_100.0;
+        let mut sin = Sin::new(sr);
+        // unconnected, it plays A4
+        let y = render(&mut sin, &[], &[], 4416).remove(0);
+        assert!((amplitude(&y[..4400], 440.0 / sr) - 1.0).abs() < 0.01);
+        // a change of frequency is continuous, and then held
+        let mut y = render(&mut sin, &[882f32.log2()], &[], 64).remove(0);
+        y.extend(render(&mut sin, &[], &[], 4416).remove(0));
+        assert!(y.windows(2).all(|w| (w[1] - w[0]).abs() < 0.13));
+        assert!((amplitude(&y[..4400], 882.0 / sr) - 1.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! A soft clipper with an adjustable knee. Below 1.0 the signal passes
+//! unchanged; above, a quadratic knee of the given width bends it smoothly
+//! (with continuous slope) onto a ceiling of `1 + knee / 2`. A knee of 0 is
+//! a hard clip at 1.0, and a very wide knee is nearly linear.
+//!
+//! The control input is the knee width. Oversampling, to further reduce
+//! aliasing at narrow knees, is chosen at construction.
+
+use module::{Module, Buffer};
+use super::oversample::Oversampler;
+
+pub struct SoftClip {
+    oversampler: Option<Oversampler>,
+}
+
+impl SoftClip {
+    pub fn new(oversample: bool) -> SoftClip {
+        SoftClip {
+            oversampler: if oversample { Some(Oversampler::new()) } else { None },
+        }
+    }
+}
+
+// The transfer function for a knee width `knee` (which must be non-negative).
+fn soft_clip(x: f32, knee: f32) -> f32 {
+    let a = x.abs();
+    let y = if a <= 1.0 {
+        a
+    } else if a < 1.0 + knee {
+        let over = a - 1.0;
+        a - over * over / (2.0 * knee)
+    } else {
+        1.0 + 0.5 * knee
+    };
+    if x < 0.0 { -y } else { y }
+}
+
+impl Module for SoftClip {
+    fn n_bufs_out(&self) -> usize { 1 }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        let knee = control_in[0].max(0.0);
+        let inb = buf_in[0].get();
+        let out = buf_out[0].get_mut();
+        for i in 0..out.len() {
+            out[i] = match self.oversampler {
+                Some(ref mut oversampler) => oversampler.process(inb[i], |x| soft_clip(x, knee)),
+                None => soft_clip(inb[i], knee),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::soft_clip;
+
+    #[test]
+    fn knee_range() {
+        let xs: Vec<f32> = (-5000..5001).map(|i| i as f32 * 0.001).collect();
+        for &x in &xs {
+            // narrow knee: hard clipping
+            assert!((soft_clip(x, 0.0) - x.max(-1.0).min(1.0)).abs() < 1e-6);
+            assert!((soft_clip(x, 0.01) - x.max(-1.0).min(1.0)).abs() < 0.005);
+            // wide knee: nearly linear
+            assert!((soft_clip(x, 100.0) - x).abs() < 0.1);
+        }
+        // monotonic, and continuous (the slope is at most 1)
+        for &knee in &[0.0, 0.1, 1.0, 10.0] {
+            for w in xs.windows(2) {
+                let dy = soft_clip(w[1], knee) - soft_clip(w[0], knee);
+                assert!(dy >= 0.0 && dy <= w[1] - w[0] + 1e-6);
+            }
+        }
+    }
+}
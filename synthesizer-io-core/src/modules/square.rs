@@ -0,0 +1,126 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! A band-limited square (pulse) wave, using PolyBLEP to smooth the edges.
+//!
+//! Control inputs are the frequency (log2 Hz) and the pulse width, as the
+//! fraction of each period spent high (0.5 if not connected). If the
+//! frequency isn't connected, the last one is held.
+
+use module::{Module, Buffer};
+use super::note_pitch::A4_LOG_FREQ;
+
+const DEFAULT_WIDTH: f32 = 0.5;
+
+pub struct Square {
+    sr_offset: f32,
+    // in cycles, 0..1
+    phase: f32,
+    freq: f32,
+}
+
+impl Square {
+    pub fn new(sample_rate: f32) -> Square {
+        Square {
+            sr_offset: -sample_rate.log2(),
+            phase: 0.0,
+            freq: A4_LOG_FREQ,
+        }
+    }
+}
+
+// The residual of a band-limited step from a naive one, at phase `t` of a
+// step at phase 0, with `dt` the phase increment per sample.
+fn poly_blep(t: f32, dt: f32) -> f32 {
+    if t < dt {
+        let t = t / dt;
+        t + t - t * t - 1.0
+    } else if t > 1.0 - dt {
+        let t = (t - 1.0) / dt;
+        t * t + t + t + 1.0
+    } else {
+        0.0
+    }
+}
+
+impl Module for Square {
+    fn n_bufs_out(&self) -> usize { 1 }
+
+    fn n_ctrl_in(&self) -> usize { 2 }
+
+    fn migrate(&mut self, old: &mut dyn Module) {
+        if let Some(old) = old.to_any().downcast_ref::<Square>() {
+            self.phase = old.phase;
+            self.freq = old.freq;
+        }
+    }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        _buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        if let Some(&freq) = control_in.first() {
+            self.freq = freq;
+        }
+        let dt = (self.freq + self.sr_offset).exp2().min(0.5);
+        let width = control_in.get(1).cloned().unwrap_or(DEFAULT_WIDTH);
+        // keep both edges at least a sample apart
+        let width = width.max(dt).min(1.0 - dt);
+        let out = buf_out[0].get_mut();
+        let mut phase = self.phase;
+        for i in 0..out.len() {
+            let naive = if phase < width { 1.0 } else { -1.0 };
+            let mut fall = phase - width;
+            if fall < 0.0 {
+                fall += 1.0;
+            }
+            out[i] = naive + poly_blep(phase, dt) - poly_blep(fall, dt);
+            phase += dt;
+            if phase >= 1.0 {
+                phase -= 1.0;
+            }
+        }
+        self.phase = phase;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+    use modules::testutil::{render, windowed_amplitude};
+    use super::Square;
+
+    #[test]
+    fn less_aliasing_than_naive() {
+        let sr = 44_100.0;
+        let f0 = 1234.5;
+        let n = 8192;
+        let y = render(&mut Square::new(sr), &[f32::log2(f0)], &[], n).remove(0);
+        let naive: Vec<f32> = (0..n)
+            .map(|i| if (i as f32 * f0 / sr).fract() < 0.5 { 1.0 } else { -1.0 })
+            .collect();
+        // the fundamental is intact
+        let fund = 4.0 / PI;
+        assert!((windowed_amplitude(&y, f0 / sr) - fund).abs() < 0.02 * fund);
+        // odd harmonics above Nyquist, reflected back down
+        let (mut alias, mut alias_naive) = (0.0, 0.0);
+        for k in (19..60).filter(|k| k % 2 == 1) {
+            let f = (k as f32 * f0 / sr).fract();
+            let f = if f > 0.5 { 1.0 - f } else { f };
+            alias += windowed_amplitude(&y, f).powi(2);
+            alias_naive += windowed_amplitude(&naive, f).powi(2);
+        }
+        assert!(alias < 0.1 * alias_naive, "{} vs {}", alias, alias_naive);
+    }
+}
@@ -0,0 +1,106 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! A stereo delay with independent times and feedback for the left and
+//! right channels, and optional cross-feed between them.
+//!
+//! Inputs and outputs are left and right. Control inputs are the left and
+//! right delay times (in seconds, up to `MAX_DELAY`), the left and right
+//! feedback (0 to 1), and the cross-feed (0 to 1): the fraction of each
+//! channel's feedback taken from the other channel. The output is the dry
+//! signal plus the echoes.
+
+use module::{Module, Buffer};
+use super::delay::DelayLine;
+
+/// The longest delay time, in seconds.
+pub const MAX_DELAY: f32 = 2.0;
+
+pub struct StereoDelay {
+    sample_rate: f32,
+    left: DelayLine,
+    right: DelayLine,
+}
+
+impl StereoDelay {
+    pub fn new(sample_rate: f32) -> StereoDelay {
+        let max_delay = (MAX_DELAY * sample_rate).ceil() as usize;
+        StereoDelay {
+            sample_rate,
+            left: DelayLine::new(max_delay),
+            right: DelayLine::new(max_delay),
+        }
+    }
+}
+
+impl Module for StereoDelay {
+    fn n_bufs_out(&self) -> usize { 2 }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        // A delay of d samples reads back the sample written d - 1 samples ago,
+        // since the read happens before the write.
+        let t_l = control_in[0].max(0.0).min(MAX_DELAY) * self.sample_rate - 1.0;
+        let t_r = control_in[1].max(0.0).min(MAX_DELAY) * self.sample_rate - 1.0;
+        let fb_l = control_in[2].max(0.0).min(1.0);
+        let fb_r = control_in[3].max(0.0).min(1.0);
+        let cross = control_in[4].max(0.0).min(1.0);
+        let in_l = buf_in[0].get();
+        let in_r = buf_in[1].get();
+        let (out_l, out_r) = buf_out.split_at_mut(1);
+        let out_l = out_l[0].get_mut();
+        let out_r = out_r[0].get_mut();
+        for i in 0..out_l.len() {
+            let y_l = self.left.read(t_l);
+            let y_r = self.right.read(t_r);
+            self.left.write(in_l[i] + fb_l * ((1.0 - cross) * y_l + cross * y_r));
+            self.right.write(in_r[i] + fb_r * ((1.0 - cross) * y_r + cross * y_l));
+            out_l[i] = in_l[i] + y_l;
+            out_r[i] = in_r[i] + y_r;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use modules::testutil::render;
+    use super::StereoDelay;
+
+    // Indices of the nonzero samples.
+    fn taps(x: &[f32]) -> Vec<usize> {
+        (0..x.len()).filter(|&i| x[i].abs() > 1e-6).collect()
+    }
+
+    #[test]
+    fn independent_times() {
+        let sr = 1000.0;
+        let mut left = vec![0.0; 512];
+        let mut right = vec![0.0; 512];
+        left[10] = 1.0;
+        right[20] = 1.0;
+        let ctrl = [0.1, 0.15, 0.5, 0.5, 0.0];
+        let y = render(&mut StereoDelay::new(sr), &ctrl, &[&left, &right], 512);
+        assert_eq!(taps(&y[0]), vec![10, 110, 210, 310, 410, 510]);
+        assert_eq!(taps(&y[1]), vec![20, 170, 320, 470]);
+        assert_eq!(y[0][110], 1.0);
+        assert_eq!(y[0][210], 0.5);
+        // with full cross-feed, the echoes alternate between the channels
+        let ctrl = [0.1, 0.15, 0.5, 0.5, 1.0];
+        let y = render(&mut StereoDelay::new(sr), &ctrl, &[&left, &vec![0.0; 512]], 512);
+        assert_eq!(taps(&y[0]), vec![10, 110, 360]);
+        assert_eq!(taps(&y[1]), vec![260, 510]);
+    }
+}
@@ -0,0 +1,95 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! A stereo enhancer using complementary comb filters. A delayed copy of the
+//! mid (L + R) signal is added to the left channel and subtracted from the
+//! right, so that one channel's comb peaks fall on the other's notches. This
+//! decorrelates the channels, widening the image, while the mono sum is
+//! left exactly as it was.
+//!
+//! Inputs and outputs are left and right. The control input is the
+//! intensity, from 0 (no effect) to 1.
+
+use module::{Module, Buffer};
+use super::delay::DelayLine;
+
+// Delay of the comb filters, in seconds.
+const DELAY: f32 = 0.012;
+
+pub struct StereoEnhance {
+    delay_samples: f32,
+    delay: DelayLine,
+}
+
+impl StereoEnhance {
+    pub fn new(sample_rate: f32) -> StereoEnhance {
+        let delay_samples = (DELAY * sample_rate).round();
+        StereoEnhance {
+            delay_samples,
+            delay: DelayLine::new(delay_samples as usize),
+        }
+    }
+}
+
+impl Module for StereoEnhance {
+    fn n_bufs_out(&self) -> usize { 2 }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        let intensity = control_in[0].max(0.0).min(1.0);
+        let in_l = buf_in[0].get();
+        let in_r = buf_in[1].get();
+        let (out_l, out_r) = buf_out.split_at_mut(1);
+        let out_l = out_l[0].get_mut();
+        let out_r = out_r[0].get_mut();
+        for i in 0..out_l.len() {
+            self.delay.write(0.5 * (in_l[i] + in_r[i]));
+            let d = intensity * self.delay.read(self.delay_samples);
+            out_l[i] = in_l[i] + d;
+            out_r[i] = in_r[i] - d;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use modules::testutil::render;
+    use super::StereoEnhance;
+
+    fn correlation(l: &[f32], r: &[f32]) -> f32 {
+        let dot = |a: &[f32], b: &[f32]| a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+        dot(l, r) / (dot(l, l) * dot(r, r)).sqrt()
+    }
+
+    #[test]
+    fn wider_and_mono_compatible() {
+        let mut seed = 1u32;
+        let noise: Vec<f32> = (0..16384).map(|_| {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (seed >> 8) as f32 / (1 << 23) as f32 - 1.0
+        }).collect();
+        // a mostly central source
+        let l: Vec<f32> = noise.iter().enumerate().map(|(i, x)| x + 0.1 * noise[i / 2]).collect();
+        let r = noise.clone();
+        assert!(correlation(&l, &r) > 0.95);
+        let y = render(&mut StereoEnhance::new(44_100.0), &[0.8], &[&l, &r], l.len());
+        let (yl, yr) = (&y[0][1024..], &y[1][1024..]);
+        assert!(correlation(yl, yr) < 0.5, "{}", correlation(yl, yr));
+        let mono_in: f32 = l[1024..].iter().zip(&r[1024..]).map(|(a, b)| (a + b).powi(2)).sum();
+        let mono_out: f32 = yl.iter().zip(yr).map(|(a, b)| (a + b).powi(2)).sum();
+        assert!((mono_out / mono_in - 1.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,75 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! Helpers for testing modules.
+
+use std::f32::consts;
+
+use module::{Module, Buffer, N_SAMPLES_PER_CHUNK};
+
+/// Run a module over whole input signals, with constant control inputs.
+/// The length of the inputs is rounded down to a whole number of chunks.
+/// Returns one signal per output buffer.
+pub fn render(module: &mut dyn Module, ctrl: &[f32], inputs: &[&[f32]], n: usize)
+    -> Vec<Vec<f32>>
+{
+    let mut inbufs: Vec<Buffer> = inputs.iter().map(|_| Buffer::default()).collect();
+    let mut outbufs: Vec<Buffer> = (0..module.n_bufs_out()).map(|_| Buffer::default()).collect();
+    let mut ctrl_out = vec![0.0; module.n_ctrl_out()];
+    let mut result = vec![Vec::with_capacity(n); outbufs.len()];
+    for chunk in 0..n / N_SAMPLES_PER_CHUNK {
+        let range = chunk * N_SAMPLES_PER_CHUNK..(chunk + 1) * N_SAMPLES_PER_CHUNK;
+        for (buf, input) in inbufs.iter_mut().zip(inputs) {
+            buf.get_mut().copy_from_slice(&input[range.clone()]);
+        }
+        let buf_in: Vec<&Buffer> = inbufs.iter().collect();
+        module.process(ctrl, &mut ctrl_out, &buf_in, &mut outbufs);
+        for (r, buf) in result.iter_mut().zip(&outbufs) {
+            r.extend_from_slice(buf.get());
+        }
+    }
+    result
+}
+
+/// A sine wave with frequency in cycles per sample.
+pub fn sine(freq: f32, amp: f32, n: usize) -> Vec<f32> {
+    (0..n).map(|i| amp * (2.0 * consts::PI * freq * i as f32).sin()).collect()
+}
+
+/// The amplitude of the component at `freq` (in cycles per sample), by
+/// direct correlation. Most accurate when the signal covers whole periods.
+pub fn amplitude(x: &[f32], freq: f32) -> f32 {
+    let (mut re, mut im) = (0.0f64, 0.0f64);
+    for (i, &x) in x.iter().enumerate() {
+        let th = 2.0 * ::std::f64::consts::PI * freq as f64 * i as f64;
+        re += x as f64 * th.cos();
+        im += x as f64 * th.sin();
+    }
+    (2.0 * (re * re + im * im).sqrt() / x.len() as f64) as f32
+}
+
+/// Like `amplitude`, but with a Hann window, so that strong components
+/// elsewhere in the spectrum don't leak into the measurement.
+pub fn windowed_amplitude(x: &[f32], freq: f32) -> f32 {
+    let n = x.len() as f64;
+    let (mut re, mut im) = (0.0f64, 0.0f64);
+    for (i, &x) in x.iter().enumerate() {
+        let w = 1.0 - (2.0 * ::std::f64::consts::PI * i as f64 / n).cos();
+        let th = 2.0 * ::std::f64::consts::PI * freq as f64 * i as f64;
+        re += w * x as f64 * th.cos();
+        im += w * x as f64 * th.sin();
+    }
+    (2.0 * (re * re + im * im).sqrt() / n) as f32
+}
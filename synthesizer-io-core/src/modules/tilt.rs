@@ -0,0 +1,123 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! A one-knob tilt EQ: boosts highs while cutting lows (or vice versa)
+//! around a pivot frequency.
+//!
+//! The signal is split by a one-pole lowpass into complementary low and high
+//! bands, which are then recombined with opposite gains; this is equivalent
+//! to a pair of matched first-order shelving filters.
+//!
+//! Control inputs are the pivot frequency (log2 of Hz) and the tilt in dB.
+//! A tilt of +6 dB gives +3 dB at high frequencies and -3 dB at low.
+
+use std::f32::consts;
+
+use module::{Module, Buffer};
+
+pub struct Tilt {
+    sr_offset: f32,
+    state: f32,
+}
+
+impl Tilt {
+    pub fn new(sample_rate: f32) -> Tilt {
+        Tilt {
+            sr_offset: consts::PI.log2() - sample_rate.log2(),
+            state: 0.0,
+        }
+    }
+}
+
+impl Module for Tilt {
+    fn n_bufs_out(&self) -> usize { 1 }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        // clamp to just below Nyquist so tan stays finite
+        let g = (control_in[0] + self.sr_offset).min((0.49 * consts::PI).log2()).exp2().tan();
+        let a = g / (1.0 + g);
+        let tilt = control_in[1];
+        let g_hi = 10f32.powf(tilt * (1.0 / 40.0));
+        let g_lo = g_hi.recip();
+        let inb = buf_in[0].get();
+        let out = buf_out[0].get_mut();
+        let mut s = self.state;
+        for i in 0..out.len() {
+            // topology-preserving one-pole lowpass
+            let x = inb[i];
+            let v = (x - s) * a;
+            let lp = v + s;
+            s = lp + v;
+            out[i] = lp * g_lo + (x - lp) * g_hi;
+        }
+        self.state = s;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+    use module::{Module, Buffer};
+    use super::Tilt;
+
+    // Gain in dB of the tilt EQ for a sine at the given frequency.
+    fn gain_db(freq: f32, tilt: f32) -> f32 {
+        gain_db_pivot(freq, 1000.0, tilt)
+    }
+
+    fn gain_db_pivot(freq: f32, pivot: f32, tilt: f32) -> f32 {
+        let sr = 44_100.0;
+        let mut eq = Tilt::new(sr);
+        let ctrl = [pivot.log2(), tilt];
+        let mut inb = Buffer::default();
+        let mut out = [Buffer::default()];
+        let (mut e_in, mut e_out) = (0.0, 0.0);
+        for chunk in 0..400 {
+            for (i, x) in inb.get_mut().iter_mut().enumerate() {
+                let t = (chunk * 32 + i) as f32 / sr;
+                *x = (2.0 * PI * freq * t).sin();
+            }
+            eq.process(&ctrl, &mut [], &[&inb], &mut out);
+            if chunk >= 100 {
+                e_in += inb.get().iter().map(|x| x * x).sum::<f32>();
+                e_out += out[0].get().iter().map(|x| x * x).sum::<f32>();
+            }
+        }
+        10.0 * (e_out / e_in).log10()
+    }
+
+    #[test]
+    fn tilt_is_symmetric() {
+        let lo = gain_db(40.0, 6.0);
+        let hi = gain_db(16_000.0, 6.0);
+        assert!((lo + 3.0).abs() < 0.2, "low gain {}", lo);
+        assert!((hi - 3.0).abs() < 0.2, "high gain {}", hi);
+        assert!((lo + hi).abs() < 0.2);
+        assert!(gain_db(1000.0, 0.0).abs() < 1e-3);
+        assert!((gain_db(40.0, -6.0) - 3.0).abs() < 0.2);
+    }
+
+    #[test]
+    fn high_pivot() {
+        // The pivot is prewarped, so the gain there doesn't depend on where
+        // it is, even well above the audio midrange.
+        let at_1k = gain_db_pivot(1000.0, 1000.0, 12.0);
+        let at_10k = gain_db_pivot(10_000.0, 10_000.0, 12.0);
+        assert!((at_1k - at_10k).abs() < 0.1, "{} vs {}", at_1k, at_10k);
+        assert!(gain_db_pivot(16_000.0, 10_000.0, 12.0) > at_10k + 1.0);
+    }
+}
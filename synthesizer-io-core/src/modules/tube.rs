@@ -0,0 +1,113 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! A tube (triode) style saturation stage.
+//!
+//! The transfer curve is asymmetric: positive excursions clip earlier and
+//! harder (like grid conduction) than negative ones (like cutoff), which
+//! produces even harmonics. The bias shifts the operating point, and under
+//! sustained load the bias sags, emulating power supply droop. The
+//! nonlinearity is oversampled and the output is DC-blocked.
+//!
+//! Control inputs are the drive (log2 of gain), bias, sag amount (0 to 1),
+//! and output level (log2 of gain).
+
+use module::{Module, Buffer};
+use super::filters::DcBlocker;
+use super::follower::{time_coef, EnvFollower};
+use super::oversample::Oversampler;
+
+// Time constants of the sag, in seconds.
+const SAG_ATTACK: f32 = 0.01;
+const SAG_RELEASE: f32 = 0.1;
+
+pub struct Tube {
+    sag_attack: f32,
+    sag_release: f32,
+    sag_env: EnvFollower,
+    oversampler: Oversampler,
+    dc: DcBlocker,
+}
+
+impl Tube {
+    pub fn new(sample_rate: f32) -> Tube {
+        Tube {
+            sag_attack: time_coef(SAG_ATTACK, sample_rate),
+            sag_release: time_coef(SAG_RELEASE, sample_rate),
+            sag_env: EnvFollower::new(),
+            oversampler: Oversampler::new(),
+            dc: DcBlocker::new(),
+        }
+    }
+}
+
+// The asymmetric triode curve: unit slope at the origin, saturating at 1.0
+// for positive input and -1.5 for negative.
+fn triode(x: f32) -> f32 {
+    if x >= 0.0 {
+        x.tanh()
+    } else {
+        1.5 * (x * (1.0 / 1.5)).tanh()
+    }
+}
+
+impl Module for Tube {
+    fn n_bufs_out(&self) -> usize { 1 }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        let drive = control_in[0].exp2();
+        let bias = control_in[1];
+        let sag = control_in[2];
+        let level = control_in[3].exp2();
+        let inb = buf_in[0].get();
+        let out = buf_out[0].get_mut();
+        for i in 0..out.len() {
+            let x = drive * inb[i];
+            let env = self.sag_env.process(x, self.sag_attack, self.sag_release);
+            let b = bias - sag * env;
+            let y = self.oversampler.process(x, |x| triode(x + b) - triode(b));
+            out[i] = level * self.dc.process(y);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use modules::testutil::{render, sine, amplitude};
+    use super::{Tube, triode};
+
+    #[test]
+    fn asymmetric_transfer() {
+        for &x in &[1.0, 2.0, 4.0] {
+            assert!(triode(x) < -triode(-x));
+        }
+        assert!((triode(0.01) + triode(-0.01)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn even_harmonics() {
+        let freq = 0.01;
+        let x = sine(freq, 1.0, 8192);
+        let mut tube = Tube::new(44_100.0);
+        let y = render(&mut tube, &[2.0, 0.0, 0.0, 0.0], &[&x], 8192).remove(0);
+        let y = &y[4096..];
+        let h1 = amplitude(y, freq);
+        let h2 = amplitude(y, 2.0 * freq);
+        assert!(h2 > 0.05 * h1, "h1 = {}, h2 = {}", h1, h2);
+        assert!(y.iter().sum::<f32>().abs() / 4096.0 < 0.01);
+    }
+}
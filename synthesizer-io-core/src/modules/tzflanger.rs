@@ -0,0 +1,132 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! A through-zero flanger.
+//!
+//! Both the dry and wet paths are delayed. The wet delay sweeps either side
+//! of the fixed dry delay, so the relative delay passes through zero. At that
+//! point the comb filter has no notches at all, giving the deep "jet" sweep
+//! that a conventional flanger (whose relative delay is always positive, and
+//! so always has a notch somewhere) can't reach.
+//!
+//! Control inputs are the LFO rate (log2 of Hz), depth (0 to 1, as a fraction
+//! of the center delay), and feedback (-1 to 1) around the wet path.
+
+use std::f32::consts;
+
+use module::{Module, Buffer};
+use super::delay::DelayLine;
+
+// The fixed delay of the dry path, in seconds.
+const CENTER_DELAY: f32 = 0.005;
+
+pub struct TzFlanger {
+    sr_offset: f32,
+    center: f32,  // in samples
+    phase: f32,  // LFO phase in cycles, 0 to 1
+    dry: DelayLine,
+    wet: DelayLine,
+    wet_last: f32,
+}
+
+impl TzFlanger {
+    pub fn new(sample_rate: f32) -> TzFlanger {
+        let center = (CENTER_DELAY * sample_rate).round();
+        let max_delay = 2 * center as usize + 1;
+        TzFlanger {
+            sr_offset: -sample_rate.log2(),
+            center,
+            phase: 0.0,
+            dry: DelayLine::new(max_delay),
+            wet: DelayLine::new(max_delay),
+            wet_last: 0.0,
+        }
+    }
+}
+
+impl Module for TzFlanger {
+    fn n_bufs_out(&self) -> usize { 1 }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        let freq = (control_in[0] + self.sr_offset).exp2();
+        let depth = control_in[1].max(0.0).min(1.0) * self.center;
+        let feedback = control_in[2].max(-0.95).min(0.95);
+        let inb = buf_in[0].get();
+        let out = buf_out[0].get_mut();
+        let mut phase = self.phase;
+        for i in 0..out.len() {
+            let x = inb[i];
+            self.dry.write(x);
+            self.wet.write(x + feedback * self.wet_last);
+            let delay = self.center + depth * (2.0 * consts::PI * phase).sin();
+            let dry = self.dry.read(self.center);
+            let wet = self.wet.read(delay);
+            self.wet_last = wet;
+            out[i] = 0.5 * (dry + wet);
+            phase += freq;
+        }
+        self.phase = phase - phase.floor();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use modules::testutil::render;
+    use super::TzFlanger;
+
+    #[test]
+    fn passes_through_zero() {
+        let sr = 44_100.0;
+        let spacing = 1024;
+        let n = 64 * spacing;
+        let x: Vec<f32> = (0..n).map(|i| if i % spacing == 0 { 1.0 } else { 0.0 }).collect();
+        let mut flanger = TzFlanger::new(sr);
+        let center = flanger.center as usize;
+        let y = render(&mut flanger, &[1f32.log2(), 1.0, 0.0], &[&x], n).remove(0);
+        // For each impulse, find the position of the wet tap relative to the dry.
+        let mut rel = Vec::new();
+        for k in 0..64 {
+            let t = k * spacing + center;
+            let (mut sum, mut moment) = (0.0, 0.0);
+            for i in k * spacing..(k + 1) * spacing {
+                let w = y[i] - if i == t { 0.5 } else { 0.0 };
+                sum += w;
+                moment += w * (i as f32 - t as f32);
+            }
+            // (not exactly 0.5, because of the Doppler effect of the sweep)
+            assert!((sum - 0.5).abs() < 0.05);
+            rel.push(moment / sum);
+        }
+        // the wet tap sweeps both before and after the dry tap
+        let min = rel.iter().cloned().fold(0.0, f32::min);
+        let max = rel.iter().cloned().fold(0.0, f32::max);
+        assert!(min < -0.9 * center as f32 && max > 0.9 * center as f32);
+    }
+
+    #[test]
+    fn flat_at_zero() {
+        // With a very slow LFO starting at phase 0, the wet and dry taps
+        // coincide, so an impulse comes out whole, i.e. the response is flat.
+        let mut flanger = TzFlanger::new(44_100.0);
+        let center = flanger.center as usize;
+        let mut x = vec![0.0; 512];
+        x[0] = 1.0;
+        let y = render(&mut flanger, &[0.001f32.log2(), 1.0, 0.5], &[&x], 512).remove(0);
+        assert!((y[center] - 1.0).abs() < 0.01);
+        assert!(y[..center].iter().all(|&y| y == 0.0));
+    }
+}
@@ -0,0 +1,129 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! A channel vocoder: a bank of bandpass filters analyzes the modulator
+//! (the first buffer input, e.g. a voice), and the envelope of each band
+//! sets the gain of the matching band of a filterbank applied to the carrier
+//! (the second buffer input, e.g. a saw).
+//!
+//! The bands are spaced logarithmically between `LOW_FREQ` and `HIGH_FREQ`.
+
+use module::{Module, Buffer};
+use super::filters::Svf;
+use super::follower::{time_coef, EnvFollower};
+
+const LOW_FREQ: f32 = 100.0;
+const HIGH_FREQ: f32 = 8000.0;
+
+// Envelope follower times, in seconds.
+const ATTACK: f32 = 0.002;
+const RELEASE: f32 = 0.02;
+
+// Each filter is a cascade of two bandpass sections, for steeper skirts.
+struct Band {
+    g: f32,
+    analysis: [Svf; 2],
+    synthesis: [Svf; 2],
+    env: EnvFollower,
+}
+
+impl Band {
+    // Apply a (unity peak gain) bandpass cascade.
+    fn bandpass(filters: &mut [Svf; 2], x: f32, g: f32, k: f32) -> f32 {
+        let (_, y, _) = filters[0].process(x, g, k);
+        let (_, y, _) = filters[1].process(y * k, g, k);
+        y * k
+    }
+}
+
+pub struct Vocoder {
+    k: f32,
+    attack: f32,
+    release: f32,
+    bands: Vec<Band>,
+}
+
+impl Vocoder {
+    /// Create a vocoder with `n_bands` bands (at least 2).
+    pub fn new(sample_rate: f32, n_bands: usize) -> Vocoder {
+        assert!(n_bands >= 2);
+        let ratio = (HIGH_FREQ / LOW_FREQ).powf(1.0 / (n_bands - 1) as f32);
+        // Set the Q of each section so its -3dB bandwidth matches the band spacing.
+        let k = ratio.sqrt() - ratio.sqrt().recip();
+        let bands = (0..n_bands).map(|i| Band {
+            g: Svf::coef(LOW_FREQ * ratio.powi(i as i32), sample_rate),
+            analysis: [Svf::new(), Svf::new()],
+            synthesis: [Svf::new(), Svf::new()],
+            env: EnvFollower::new(),
+        }).collect();
+        Vocoder {
+            k,
+            attack: time_coef(ATTACK, sample_rate),
+            release: time_coef(RELEASE, sample_rate),
+            bands,
+        }
+    }
+}
+
+impl Module for Vocoder {
+    fn n_bufs_out(&self) -> usize { 1 }
+
+    fn process(&mut self, _control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        let modulator = buf_in[0].get();
+        let carrier = buf_in[1].get();
+        let out = buf_out[0].get_mut();
+        for i in 0..out.len() {
+            let mut y = 0.0;
+            for band in &mut self.bands {
+                let m = Band::bandpass(&mut band.analysis, modulator[i], band.g, self.k);
+                let env = band.env.process(m, self.attack, self.release);
+                let c = Band::bandpass(&mut band.synthesis, carrier[i], band.g, self.k);
+                y += c * env;
+            }
+            out[i] = y;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use modules::testutil::{render, sine, amplitude};
+    use super::{Vocoder, LOW_FREQ, HIGH_FREQ};
+
+    #[test]
+    fn imposes_band_envelope() {
+        let sr = 44_100.0;
+        let n_bands = 16;
+        let ratio = (HIGH_FREQ / LOW_FREQ).powf(1.0 / (n_bands - 1) as f32);
+        let f_b = LOW_FREQ * ratio.powi(5) / sr;
+        let f_c = LOW_FREQ * ratio.powi(12) / sr;
+        let n = 16384;
+        // modulator in band b, on for the first half only
+        let modulator: Vec<f32> = sine(f_b, 1.0, n).iter().enumerate()
+            .map(|(i, x)| if i < n / 2 { *x } else { 0.0 }).collect();
+        let carrier: Vec<f32> = sine(f_b, 0.5, n).iter().zip(sine(f_c, 0.5, n))
+            .map(|(x, y)| x + y).collect();
+        let y = render(&mut Vocoder::new(sr, n_bands), &[], &[&modulator, &carrier], n)
+            .remove(0);
+        let on = &y[n / 4..n / 2];
+        let off = &y[3 * n / 4..];
+        let on_b = amplitude(on, f_b);
+        assert!(on_b > 0.1, "band b: {}", on_b);
+        assert!(amplitude(off, f_b) < 0.01 * on_b);
+        assert!(amplitude(on, f_c) < 0.01 * on_b);
+    }
+}
@@ -0,0 +1,95 @@
+// Copyright 2018 The Synthesizer IO Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! A West-coast style wavefolder: the signal is reflected back on itself
+//! whenever it exceeds the range [-1, 1], generating rich harmonics as the
+//! drive increases. Processing is oversampled to tame aliasing.
+//!
+//! Control inputs are the fold amount (linear drive; 1 means no folding for
+//! a full-scale input) and a DC offset applied before folding, which makes
+//! the folding asymmetric.
+
+use module::{Module, Buffer};
+use super::oversample::Oversampler;
+
+pub struct WaveFold {
+    oversampler: Oversampler,
+}
+
+impl WaveFold {
+    pub fn new() -> WaveFold {
+        WaveFold {
+            oversampler: Oversampler::new(),
+        }
+    }
+}
+
+// Triangle-shaped transfer function with period 4, equal to x on [-1, 1].
+fn fold(x: f32) -> f32 {
+    let t = (x + 1.0) * 0.25;
+    let t = 4.0 * (t - t.floor());
+    if t < 2.0 { t - 1.0 } else { 3.0 - t }
+}
+
+impl Module for WaveFold {
+    fn n_bufs_out(&self) -> usize { 1 }
+
+    fn process(&mut self, control_in: &[f32], _control_out: &mut [f32],
+        buf_in: &[&Buffer], buf_out: &mut [Buffer])
+    {
+        let drive = control_in[0];
+        let offset = control_in[1];
+        let inb = buf_in[0].get();
+        let out = buf_out[0].get_mut();
+        for i in 0..out.len() {
+            out[i] = self.oversampler.process(inb[i], |x| fold(drive * x + offset));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use modules::testutil::{render, sine, amplitude};
+    use super::{WaveFold, fold};
+
+    // Fraction of the harmonic amplitude (up to 16) in harmonics above the 3rd.
+    fn high_harmonics(drive: f32) -> f32 {
+        let freq = 0.01;
+        let x = sine(freq, 1.0, 4096);
+        let y = render(&mut WaveFold::new(), &[drive, 0.0], &[&x], 4096).remove(0);
+        assert!(y.iter().all(|y| y.abs() < 1.05));
+        let y = &y[96..4096];
+        let total: f32 = (1..16).map(|k| amplitude(y, k as f32 * freq)).sum();
+        let high: f32 = (4..16).map(|k| amplitude(y, k as f32 * freq)).sum();
+        high / total
+    }
+
+    #[test]
+    fn fold_transfer() {
+        assert_eq!(fold(0.5), 0.5);
+        assert_eq!(fold(1.5), 0.5);
+        assert_eq!(fold(-1.5), -0.5);
+        assert_eq!(fold(3.5), -0.5);
+    }
+
+    #[test]
+    fn more_fold_more_harmonics() {
+        let h1 = high_harmonics(0.9);
+        let h2 = high_harmonics(2.0);
+        let h3 = high_harmonics(4.0);
+        assert!(h1 < 0.01, "h1 = {}", h1);
+        assert!(h1 < h2 && h2 < h3, "{} {} {}", h1, h2, h3);
+    }
+}